@@ -0,0 +1,201 @@
+// Copyright Andrey Zelenskiy, 2025
+use proc_macro::TokenStream;
+
+use proc_macro2::TokenStream as TokenStream2;
+
+use quote::{format_ident, quote};
+
+use syn::{
+    parse_macro_input, Data, DeriveInput, Expr, Field, Fields, LitStr, Token,
+};
+
+/* ----------------------------------- */
+/* Derive macro for builder structures */
+/* ----------------------------------- */
+
+/// Derives a `<Name>Builder` structure with `set_<field>` methods, together
+/// with the `BuilderMethods` and `TargetFromBuilder` implementations.
+///
+/// Fields annotated with `#[builder(default = <expr>)]` (or
+/// `#[builder(default)]` for `Default::default()`) are initialized with the
+/// given value and may be omitted from the config.
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_builder(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_builder(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let target = &input.ident;
+    let vis = &input.vis;
+    let builder = format_ident!("{}Builder", target);
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Builder cannot be derived for generic structures",
+        ));
+    }
+
+    let fields =
+        match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => &fields.named,
+                _ => return Err(syn::Error::new_spanned(
+                    target,
+                    "Builder can only be derived for structures with named \
+                    fields",
+                )),
+            },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    target,
+                    "Builder can only be derived for structures",
+                ))
+            }
+        };
+
+    let mut builder_fields = Vec::new();
+    let mut default_fns = Vec::new();
+    let mut default_values = Vec::new();
+    let mut setters = Vec::new();
+    let mut build_values = Vec::new();
+
+    for field in fields {
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+
+        match parse_default(field)? {
+            Some(value) => {
+                // Serde needs a path to a function returning the default
+                let default_fn = format_ident!("__default_{}", name);
+                let default_path = format!("{builder}::{default_fn}");
+
+                builder_fields.push(quote! {
+                    #[serde(default = #default_path)]
+                    #name: #ty
+                });
+                default_fns.push(quote! {
+                    fn #default_fn() -> #ty {
+                        #value
+                    }
+                });
+                default_values.push(quote! { #name: Self::#default_fn() });
+            }
+            None => {
+                builder_fields.push(quote! { #name: #ty });
+                default_values.push(quote! {
+                    #name: ::core::default::Default::default()
+                });
+            }
+        }
+
+        let setter = format_ident!("set_{}", name);
+        setters.push(quote! {
+            pub fn #setter(&mut self, #name: #ty) -> &mut Self {
+                self.#name = #name;
+                self
+            }
+        });
+
+        build_values.push(quote! {
+            #name: ::core::clone::Clone::clone(&self.#name)
+        });
+    }
+
+    let doc = format!("Builder for [`{target}`]");
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(::io_utils::__private::serde::Deserialize)]
+        #[serde(crate = "::io_utils::__private::serde")]
+        #vis struct #builder {
+            #(#builder_fields,)*
+        }
+
+        impl #builder {
+            #(#default_fns)*
+        }
+
+        impl ::core::default::Default for #builder {
+            fn default() -> Self {
+                Self {
+                    #(#default_values,)*
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #builder {
+            #(#setters)*
+        }
+
+        impl ::io_utils::initialize::BuilderMethods for #builder {
+            type Target = #target;
+
+            fn build(&mut self) -> Self::Target {
+                #target {
+                    #(#build_values,)*
+                }
+            }
+        }
+
+        impl ::io_utils::initialize::TargetFromBuilder for #target {
+            type Builder = #builder;
+        }
+    })
+}
+
+// Parse the value of the #[builder(default)] attribute of a field
+fn parse_default(field: &Field) -> syn::Result<Option<TokenStream2>> {
+    let mut default = None;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("builder")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = Some(if meta.input.peek(Token![=]) {
+                    let value: Expr = meta.value()?.parse()?;
+                    quote! { #value }
+                } else {
+                    quote! { ::core::default::Default::default() }
+                });
+                Ok(())
+            } else {
+                Err(meta.error("unsupported builder attribute"))
+            }
+        })?;
+    }
+
+    Ok(default)
+}
+
+/* -------------------------------------- */
+/* Attribute macro for config table names */
+/* -------------------------------------- */
+
+/// Implements `ConfigTable` for the structure with the given table name, so
+/// that `from_config_table` reads it from that table of a config, e.g.
+/// `#[config_table("project")]`.
+#[proc_macro_attribute]
+pub fn config_table(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let table_name = parse_macro_input!(attr as LitStr);
+    let input = parse_macro_input!(item as DeriveInput);
+
+    let target = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    quote! {
+        #input
+
+        impl #impl_generics ::io_utils::config_parse::ConfigTable
+            for #target #ty_generics #where_clause
+        {
+            const TABLE_NAME: &'static str = #table_name;
+        }
+    }
+    .into()
+}
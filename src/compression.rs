@@ -0,0 +1,214 @@
+// Copyright Andrey Zelenskiy, 2025
+use std::fs;
+
+use std::io::{self, BufRead, Read, Write};
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Default compression level of zstd (the level used by the zstd CLI)
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Compression codec of output files, each codec is enabled by the cargo
+/// feature of the same name
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Compression {
+    // gzip at the default level (6)
+    #[cfg(feature = "gzip")]
+    Gzip,
+    // zstd at the given level (1-22)
+    #[cfg(feature = "zstd")]
+    Zstd {
+        #[serde(default = "default_zstd_level")]
+        level: i32,
+    },
+    // bzip2 at the default level (6)
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+}
+
+#[cfg(feature = "zstd")]
+fn default_zstd_level() -> i32 {
+    DEFAULT_ZSTD_LEVEL
+}
+
+impl Compression {
+    /// Returns zstd compression at the default level
+    #[cfg(feature = "zstd")]
+    pub fn zstd() -> Self {
+        Compression::Zstd {
+            level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+
+    /// Returns the extension appended to compressed files (without a dot)
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gz",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => "zst",
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => "bz2",
+        }
+    }
+
+    /// Detects the compression from the extension of the path (.gz, .zst or
+    /// .bz2), the level of zstd is irrelevant for decoding
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Some(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            Some("zst") => Some(Compression::zstd()),
+            #[cfg(feature = "bzip2")]
+            Some("bz2") => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Wraps the writer in an encoder, the compressed stream is finished
+    /// when the encoder is dropped
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "zstd", feature = "bzip2")),
+        allow(unused_variables)
+    )]
+    pub fn encoder<'a, W: Write + 'a>(
+        &self,
+        writer: W,
+    ) -> io::Result<Box<dyn Write + 'a>> {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Box::new(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level } => {
+                Ok(Box::new(zstd::Encoder::new(writer, level)?.auto_finish()))
+            }
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Ok(Box::new(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            ))),
+        }
+    }
+
+    /// Wraps the reader in a decoder, concatenated streams (e.g. written by
+    /// several encoders appending to the same file) are read in sequence
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "zstd", feature = "bzip2")),
+        allow(unused_variables)
+    )]
+    pub fn decoder<'a, R: BufRead + 'a>(
+        &self,
+        reader: R,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => {
+                Ok(Box::new(zstd::Decoder::with_buffer(reader)?))
+            }
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => {
+                Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(reader)))
+            }
+        }
+    }
+}
+
+/// Opens a file in a buffer to read the data, decompressing it if the
+/// extension matches one of the enabled codecs
+pub fn open_reader(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let reader = io::BufReader::new(fs::File::open(path)?);
+
+    match Compression::from_path(path) {
+        Some(compression) => {
+            Ok(Box::new(io::BufReader::new(compression.decoder(reader)?)))
+        }
+        None => Ok(Box::new(reader)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open_reader, Compression};
+
+    use std::fs;
+
+    use std::io::BufRead;
+
+    use crate::files::OverwriteType;
+
+    use crate::testing::TempProject;
+
+    // Writes two streams to the file with the name in a temporary directory
+    // and reads them back
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+    fn round_trip(compression: Compression, name: &str) {
+        use std::io::Write;
+
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let path = &temp.path().join(name);
+        assert_eq!(Some(compression), Compression::from_path(path));
+
+        for line in ["first line", "second line"] {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap();
+            let mut encoder = compression.encoder(file).unwrap();
+            writeln!(encoder, "{line}").unwrap();
+        }
+
+        // The file is actually compressed
+        assert!(!fs::read(path).unwrap().starts_with(b"first"));
+
+        let lines: Vec<String> = open_reader(path)
+            .unwrap()
+            .lines()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec!["first line", "second line"], lines);
+    }
+
+    #[test]
+    fn plain_file() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let path = &temp.path().join("plain_file.dat");
+        fs::write(path, "line\n").unwrap();
+
+        assert_eq!(None, Compression::from_path(path));
+        let lines: Vec<String> = open_reader(path)
+            .unwrap()
+            .lines()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec!["line"], lines);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        round_trip(Compression::Gzip, "gzip.dat.gz");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        round_trip(Compression::zstd(), "zstd.dat.zst");
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn bzip2() {
+        round_trip(Compression::Bzip2, "bzip2.dat.bz2");
+    }
+}
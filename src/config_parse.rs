@@ -1,170 +1,2979 @@
 // Copyright Andrey Zelenskiy, 2024
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 
-use std::path::Path;
+use std::{fmt, fs, io};
+
+use std::cell::RefCell;
+
+use std::ops::Deref;
+
+use std::path::{Path, PathBuf};
+
+use std::sync::Arc;
+
+#[cfg(feature = "watch")]
+use std::{sync::mpsc, thread, time::Duration};
 
 use toml;
 
-use serde_json;
+use serde_json;
+
+use serde::Deserialize;
+
+use crate::error::Error;
+
+#[cfg(feature = "derive")]
+pub use io_utils_derive::config_table;
+
+/* ------------------------------ */
+/* Generic initialization methods */
+/* ------------------------------ */
+
+// Parsed config file, remembers the file it was loaded from (if any) to
+// point at it in error messages
+#[derive(Clone)]
+pub struct Config {
+    data: ConfigData,
+    source_path: Option<PathBuf>,
+}
+
+// Config shared between threads, cloning it only bumps the reference count
+// instead of copying the contents. Derefs to Config, so the accessors and
+// FromConfig work on it directly
+#[derive(Clone)]
+pub struct SharedConfig(Arc<Config>);
+
+// Delay without further changes after which a watched config is reloaded
+#[cfg(feature = "watch")]
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Handle of a config file watched by Config::watch, the watching stops when
+// it is dropped
+#[cfg(feature = "watch")]
+pub struct ConfigWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+// Contents of a config. With the default `preserve_order` feature the keys
+// are kept in the order they appear in the source, otherwise they are sorted.
+#[derive(Clone)]
+pub enum ConfigData {
+    Toml(toml::Table),
+    Json(serde_json::Value),
+}
+
+// Accumulates key/value pairs into a toml Config, values can be anything
+// convertible into a toml::Value (including nested toml::Table)
+#[derive(Clone, Default)]
+pub struct ConfigBuilder {
+    table: toml::Table,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(
+        &mut self,
+        key: &str,
+        value: impl Into<toml::Value>,
+    ) -> &mut Self {
+        self.table.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn build(&self) -> Config {
+        Config::from(self.table.clone())
+    }
+}
+
+// Resolves a config from layered sources. Every source overrides the ones
+// added before it, so the sources are added from the lowest to the highest
+// priority; the standard order is
+//   defaults < config file(s) < environment < command-line overrides
+// Nested tables are merged key by key (see Config::merge), the result has
+// the format of the first source (an empty toml config without sources).
+#[derive(Clone, Default)]
+pub struct ConfigResolver {
+    sources: Vec<ConfigSource>,
+}
+
+// Source of a ConfigResolver layer
+#[derive(Clone)]
+enum ConfigSource {
+    Config(Config),
+    File(PathBuf),
+    // Prefix of the variables, and the variables (the environment of the
+    // process when resolving if not given)
+    Env(String, Option<Vec<(String, String)>>),
+    Overrides(Vec<(String, String)>),
+}
+
+impl ConfigResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a config given in code (e.g. the defaults of the application)
+    pub fn with_config(&mut self, config: Config) -> &mut Self {
+        self.sources.push(ConfigSource::Config(config));
+        self
+    }
+
+    // Add a config file, resolving fails if it cannot be loaded
+    pub fn with_file(&mut self, path: &Path) -> &mut Self {
+        self.sources.push(ConfigSource::File(path.to_path_buf()));
+        self
+    }
+
+    // Add the environment variables with the prefix (read when resolving),
+    // the rest of the name is the key in lowercase with "__" separating the
+    // nested tables: APP_DATABASE__PORT=5432 sets database.port for "APP"
+    pub fn with_env(&mut self, prefix: &str) -> &mut Self {
+        self.sources
+            .push(ConfigSource::Env(prefix.to_string(), None));
+        self
+    }
+
+    // Add the variables with the prefix as with_env, taking the variables
+    // (name, value) instead of the environment of the process, e.g. to test
+    // the resolution without modifying the environment
+    pub fn with_env_vars<K: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        prefix: &str,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self {
+        let vars = vars
+            .into_iter()
+            .map(|(name, value)| {
+                (name.as_ref().to_string(), value.as_ref().to_string())
+            })
+            .collect();
+        self.sources
+            .push(ConfigSource::Env(prefix.to_string(), Some(vars)));
+        self
+    }
+
+    // Add values by dotted key (e.g. "database.port" = "5432" from a --set
+    // flag)
+    pub fn with_overrides<K: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        overrides: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self {
+        let overrides = overrides
+            .into_iter()
+            .map(|(key, value)| {
+                (key.as_ref().to_string(), value.as_ref().to_string())
+            })
+            .collect();
+        self.sources.push(ConfigSource::Overrides(overrides));
+        self
+    }
+
+    // Merge the sources in the order they were added. Values of the
+    // environment and the overrides are read as toml values (5432, true,
+    // [1, 2], "quoted"), anything else is kept as a string
+    pub fn resolve(&self) -> Result<Config, Error> {
+        let mut resolved: Option<Config> = None;
+
+        for source in &self.sources {
+            let layer = match source {
+                ConfigSource::Config(config) => config.clone(),
+                ConfigSource::File(path) => try_load_config(path)?,
+                ConfigSource::Env(prefix, vars) => {
+                    let prefix = format!("{prefix}_");
+                    let vars = match vars {
+                        Some(vars) => vars.clone(),
+                        None => std::env::vars().collect(),
+                    };
+                    let entries: Vec<(String, String)> = vars
+                        .into_iter()
+                        .filter_map(|(name, value)| {
+                            let key = name.strip_prefix(&prefix)?;
+                            Some((key.to_lowercase().replace("__", "."), value))
+                        })
+                        .collect();
+                    overrides_config(&entries)
+                }
+                ConfigSource::Overrides(entries) => overrides_config(entries),
+            };
+
+            match &mut resolved {
+                Some(config) => config.merge(&layer)?,
+                None => resolved = Some(layer),
+            }
+        }
+
+        Ok(resolved.unwrap_or_else(|| Config::from(toml::Table::new())))
+    }
+}
+
+// Borrowed value of a config entry
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigValue<'a> {
+    Toml(&'a toml::Value),
+    Json(&'a serde_json::Value),
+}
+
+impl<'a> From<&'a toml::Value> for ConfigValue<'a> {
+    fn from(value: &'a toml::Value) -> Self {
+        Self::Toml(value)
+    }
+}
+
+impl<'a> From<&'a serde_json::Value> for ConfigValue<'a> {
+    fn from(value: &'a serde_json::Value) -> Self {
+        Self::Json(value)
+    }
+}
+
+// Formats of the config files (YAML is not supported), json5 files are
+// parsed into JSON configs
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toml => write!(f, "TOML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+// Difference of an entry between two configs, keys of nested entries are
+// dotted paths (e.g. "database.port")
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigDiff<'a> {
+    Added {
+        key: String,
+        value: ConfigValue<'a>,
+    },
+    Removed {
+        key: String,
+        value: ConfigValue<'a>,
+    },
+    Changed {
+        key: String,
+        old: ConfigValue<'a>,
+        new: ConfigValue<'a>,
+    },
+}
+
+impl ConfigDiff<'_> {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Added { key, .. }
+            | Self::Removed { key, .. }
+            | Self::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl From<ConfigData> for Config {
+    fn from(data: ConfigData) -> Self {
+        Self {
+            data,
+            source_path: None,
+        }
+    }
+}
+
+impl From<toml::Table> for Config {
+    fn from(value: toml::Table) -> Self {
+        Self::from(ConfigData::Toml(value))
+    }
+}
+
+impl From<serde_json::Value> for Config {
+    fn from(value: serde_json::Value) -> Self {
+        Self::from(ConfigData::Json(value))
+    }
+}
+
+impl From<Config> for SharedConfig {
+    fn from(config: Config) -> Self {
+        Self(Arc::new(config))
+    }
+}
+
+impl Deref for SharedConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.0
+    }
+}
+
+impl Config {
+    // Share the config between threads without copying it
+    pub fn into_shared(self) -> SharedConfig {
+        SharedConfig::from(self)
+    }
+
+    // Contents of the config
+    pub fn data(&self) -> &ConfigData {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut ConfigData {
+        &mut self.data
+    }
+
+    // Format of the contents (the one of the source for a loaded config)
+    pub fn format(&self) -> Format {
+        match self.data {
+            ConfigData::Toml(_) => Format::Toml,
+            ConfigData::Json(_) => Format::Json,
+        }
+    }
+
+    // File the config was loaded from (None if created from a string or in
+    // memory)
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source_path.as_deref()
+    }
+
+    pub fn set_source_path(&mut self, source_path: &Path) -> &mut Self {
+        self.source_path = Some(source_path.to_path_buf());
+        self
+    }
+
+    // Initialize Config from strings
+    pub fn from_toml_str(config_str: &str) -> Self {
+        Self::try_from_toml_str(config_str).unwrap_or_else(|e| {
+            panic!("Unable to parse toml config string {config_str}: {e}")
+        })
+    }
+
+    pub fn from_json_str(config_str: &str) -> Self {
+        Self::try_from_json_str(config_str).unwrap_or_else(|e| {
+            panic!("Unable to parse json string {config_str}: {e}")
+        })
+    }
+
+    // Fallible versions of from_toml_str and from_json_str
+    pub fn try_from_toml_str(config_str: &str) -> Result<Self, Error> {
+        match config_str.parse::<toml::Table>() {
+            Ok(table) => Ok(Self::from(table)),
+            Err(e) => Err(Error::ParseStr {
+                format: Format::Toml,
+                reason: e.message().to_string(),
+            }),
+        }
+    }
+
+    // An empty (or blank) string is an empty object like an empty TOML
+    // string is an empty table
+    pub fn try_from_json_str(config_str: &str) -> Result<Self, Error> {
+        match parse_json(config_str) {
+            Ok(value) => Ok(Self::from(value)),
+            Err(e) => Err(Error::ParseStr {
+                format: Format::Json,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    // Initialize Config from bytes embedded in the binary, e.g. a default
+    // config included with include_bytes!
+    pub fn from_embedded(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        let config_str =
+            std::str::from_utf8(bytes).map_err(|e| Error::ParseStr {
+                format,
+                reason: e.to_string(),
+            })?;
+
+        match format {
+            Format::Toml => Self::try_from_toml_str(config_str),
+            Format::Json => Self::try_from_json_str(config_str),
+        }
+    }
+
+    // Initialize a toml Config from key/value pairs (later pairs replace
+    // earlier ones with the same key)
+    pub fn from_pairs(pairs: &[(&str, toml::Value)]) -> Self {
+        Self::from(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect::<toml::Table>(),
+        )
+    }
+
+    #[cfg(feature = "json5")]
+    pub fn from_json5_str(config_str: &str) -> Self {
+        Self::try_from_json5_str(config_str).unwrap_or_else(|e| {
+            panic!("Unable to parse json5 string {config_str}: {e}")
+        })
+    }
+
+    // Fallible version of from_json5_str (errors are reported as JSON)
+    #[cfg(feature = "json5")]
+    pub fn try_from_json5_str(config_str: &str) -> Result<Self, Error> {
+        match parse_json5(config_str) {
+            Ok(value) => Ok(Self::from(value)),
+            Err(e) => Err(Error::ParseStr {
+                format: Format::Json,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    // Write the config to a file in its own format
+    pub fn to_file(&self, path: &Path) -> io::Result<()> {
+        let contents = match &self.data {
+            ConfigData::Toml(config) => toml::to_string(config)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            ConfigData::Json(config) => {
+                serde_json::to_string_pretty(config)? + "\n"
+            }
+        };
+
+        fs::write(path, contents)
+    }
+
+    // Convert the config to another format. Values without an equivalent
+    // in the target format are reported as errors instead of being changed:
+    // TOML datetimes, JSON nulls, integers outside of the i64 range and
+    // non-finite floats in JSON.
+    pub fn to_format(&self, target: Format) -> Result<Config, Error> {
+        let data = match (&self.data, target) {
+            (ConfigData::Toml(_), Format::Toml)
+            | (ConfigData::Json(_), Format::Json) => self.data.clone(),
+            (ConfigData::Toml(config), Format::Json) => {
+                let mut object = serde_json::Map::new();
+                for (key, value) in config {
+                    object.insert(key.clone(), toml_to_json(key, value)?);
+                }
+                ConfigData::Json(serde_json::Value::Object(object))
+            }
+            (ConfigData::Json(config), Format::Toml) => {
+                match json_to_toml("", config)? {
+                    toml::Value::Table(table) => ConfigData::Toml(table),
+                    _ => {
+                        return Err(Error::Conversion {
+                            key: String::new(),
+                            reason: String::from(
+                                "the root of a TOML config must be a table",
+                            ),
+                        })
+                    }
+                }
+            }
+        };
+
+        Ok(Config {
+            data,
+            source_path: self.source_path.clone(),
+        })
+    }
+
+    // Overlay another config: nested tables are merged key by key, all
+    // other values of the overlay replace the values of self. An overlay in
+    // the other format is converted first (see to_format)
+    pub fn merge(&mut self, overlay: &Config) -> Result<(), Error> {
+        let overlay = overlay.to_format(self.format())?;
+
+        match (&mut self.data, overlay.data) {
+            (ConfigData::Toml(base), ConfigData::Toml(overlay)) => {
+                merge_toml(base, overlay)
+            }
+            (ConfigData::Json(base), ConfigData::Json(overlay)) => {
+                merge_json(base, overlay)
+            }
+            _ => unreachable!("overlay was converted to the format of self"),
+        }
+        Ok(())
+    }
+
+    // Load every supported config file of the directory (e.g. conf.d) and
+    // merge them in the order of the file names, later files overriding the
+    // earlier ones. Files with other extensions are skipped, the formats may
+    // be mixed (the result has the format of the first file). An empty
+    // directory gives an empty toml config
+    pub fn from_dir(dir: &Path) -> Result<Config, Error> {
+        let io_error = |reason: io::Error| Error::Parse {
+            path: dir.to_path_buf(),
+            reason: reason.to_string(),
+        };
+
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            let supported = matches!(extension, Some("toml" | "json"))
+                || matches!(extension, Some("json5") if cfg!(feature = "json5"));
+
+            if path.is_file() && supported {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut config = Config::from(toml::Table::new());
+        for (i, path) in paths.iter().enumerate() {
+            let layer = try_load_config(path)?;
+            if i == 0 {
+                config = layer;
+            } else {
+                config.merge(&layer)?;
+            }
+        }
+
+        config.set_source_path(dir);
+        Ok(config)
+    }
+
+    // Reload the config file whenever it changes and pass the new config to
+    // the callback (called on a background thread). Successive writes within
+    // WATCH_DEBOUNCE of each other are reloaded once, and a config that fails
+    // to load (e.g. a half-written file) is logged and skipped. The directory
+    // of the file is watched, so replacing the file (as editors do) counts
+    // as a change
+    #[cfg(feature = "watch")]
+    pub fn watch(
+        path: &Path,
+        mut callback: impl FnMut(Config) + Send + 'static,
+    ) -> Result<ConfigWatcher, Error> {
+        use notify::Watcher;
+
+        let watch_error =
+            |reason: notify::Error| Error::Io(io::Error::other(reason));
+
+        let path = path.to_path_buf();
+        let file_name = match path.file_name() {
+            Some(file_name) => file_name.to_os_string(),
+            None => {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a file", path.display()),
+                )))
+            }
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(
+            move |event: notify::Result<notify::Event>| {
+                // The receiver is gone only once the thread has stopped
+                let _ = sender.send(event);
+            },
+        )
+        .map_err(watch_error)?;
+        watcher
+            .watch(&dir, notify::RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+
+        // The thread stops when the watcher (and its sender) is dropped
+        thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                match event {
+                    // Reading the file (also by the reload) is not a change
+                    Ok(event)
+                        if !event.kind.is_access()
+                            && event.paths.iter().any(|changed| {
+                                changed.file_name() == Some(&file_name)
+                            }) => {}
+                    Ok(_) => continue,
+                    Err(reason) => {
+                        log::warn!(
+                            "Error watching config {}: {reason}",
+                            path.display()
+                        );
+                        continue;
+                    }
+                }
+
+                // Wait until the writes settle
+                loop {
+                    match receiver.recv_timeout(WATCH_DEBOUNCE) {
+                        Ok(_) => continue,
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                match try_load_config(&path) {
+                    Ok(config) => callback(config),
+                    Err(reason) => {
+                        log::warn!("Skipping the changed config: {reason}")
+                    }
+                }
+            }
+        });
+
+        Ok(ConfigWatcher { _watcher: watcher })
+    }
+
+    // Log a warning for every deprecated key (dotted keys reach into nested
+    // tables) present in the config, suggesting its replacement. Returns the
+    // deprecated keys that were found
+    pub fn warn_deprecated(&self, mapping: &[(&str, &str)]) -> Vec<String> {
+        let location = match &self.source_path {
+            Some(path) => format!(" in {}", path.display()),
+            None => String::new(),
+        };
+
+        mapping
+            .iter()
+            .filter(|(old, _)| match &self.data {
+                ConfigData::Toml(config) => lookup_toml(config, old).is_some(),
+                ConfigData::Json(config) => lookup_tree(config, old).is_some(),
+            })
+            .map(|(old, new)| {
+                log::warn!(
+                    "Config key {old:?}{location} is deprecated, use {new:?} \
+                    instead"
+                );
+                old.to_string()
+            })
+            .collect()
+    }
+
+    // Copy the values of the deprecated keys to their replacements (creating
+    // the missing tables), keys that are already set are left untouched.
+    // Returns the deprecated keys that were migrated
+    pub fn migrate_deprecated(
+        &mut self,
+        mapping: &[(&str, &str)],
+    ) -> Vec<String> {
+        match &mut self.data {
+            ConfigData::Toml(config) => {
+                let mut root = toml::Value::Table(std::mem::take(config));
+                let migrated = mapping
+                    .iter()
+                    .filter(|(old, new)| migrate_tree(&mut root, old, new))
+                    .map(|(old, _)| old.to_string())
+                    .collect();
+
+                if let toml::Value::Table(table) = root {
+                    *config = table;
+                }
+                migrated
+            }
+            ConfigData::Json(config) => mapping
+                .iter()
+                .filter(|(old, new)| migrate_tree(config, old, new))
+                .map(|(old, _)| old.to_string())
+                .collect(),
+        }
+    }
+
+    // Resolve ${key} and ${nested.key} references among the string values,
+    // non-string scalars are inserted in their string form
+    pub fn interpolate(&mut self) -> Result<(), Error> {
+        match &mut self.data {
+            ConfigData::Toml(config) => {
+                let mut root = toml::Value::Table(std::mem::take(config));
+                let result = interpolate_tree(&mut root);
+
+                if let toml::Value::Table(table) = root {
+                    *config = table;
+                }
+                result
+            }
+            ConfigData::Json(config) => interpolate_tree(config),
+        }
+    }
+
+    // Top-level keys of the config. Both variants list them in the file
+    // order with the default `preserve_order` feature and sorted without it,
+    // a json config that is not an object has no keys.
+    pub fn keys(&self) -> Vec<String> {
+        match &self.data {
+            ConfigData::Toml(config) => config.keys().cloned().collect(),
+            ConfigData::Json(config) => match config.as_object() {
+                Some(object) => object.keys().cloned().collect(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    // Check if the top-level key is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        match &self.data {
+            ConfigData::Toml(config) => config.contains_key(key),
+            ConfigData::Json(config) => config.get(key).is_some(),
+        }
+    }
+
+    // Check that the top-level keys are present, reports all missing ones
+    pub fn require_keys(&self, keys: &[&str]) -> Result<(), Error> {
+        let missing: Vec<String> = keys
+            .iter()
+            .filter(|key| !self.contains_key(key))
+            .map(|key| key.to_string())
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MissingKeys {
+                keys: missing,
+                source_path: self.source_path.clone(),
+            })
+        }
+    }
+
+    // Look up a top-level key ignoring differences in the naming style.
+    // Keys are compared after dropping '_' and '-' and lowercasing, so
+    // "Output_Dir", "output_dir", "output-dir" and "outputDir" all match.
+    // An exact match is preferred, otherwise the first match in the
+    // iteration order of the underlying map is returned.
+    pub fn get_insensitive(&self, key: &str) -> Option<ConfigValue<'_>> {
+        let normalized = normalize_key(key);
+
+        match &self.data {
+            ConfigData::Toml(config) => config
+                .get(key)
+                .or_else(|| {
+                    config
+                        .iter()
+                        .find(|(name, _)| normalize_key(name) == normalized)
+                        .map(|(_, value)| value)
+                })
+                .map(ConfigValue::Toml),
+            ConfigData::Json(config) => config
+                .get(key)
+                .or_else(|| {
+                    config.as_object()?.iter().find_map(|(name, value)| {
+                        (normalize_key(name) == normalized).then_some(value)
+                    })
+                })
+                .map(ConfigValue::Json),
+        }
+    }
+
+    // Entries that differ between the configs, tables are compared entry by
+    // entry, any other values (including arrays) as a whole. The removed and
+    // changed entries come in the order of self, followed by the added ones.
+    // Configs of different formats are only compared at the top level, the
+    // values of the common keys are reported as changed.
+    pub fn diff<'a>(&'a self, other: &'a Config) -> Vec<ConfigDiff<'a>> {
+        let mut diffs = Vec::new();
+
+        match (&self.data, &other.data) {
+            (ConfigData::Toml(old), ConfigData::Toml(new)) => diff_entries(
+                "",
+                table_entries(old),
+                table_entries(new),
+                &mut diffs,
+            ),
+            (ConfigData::Json(old), ConfigData::Json(new)) => {
+                diff_entries("", old.children(), new.children(), &mut diffs)
+            }
+            (ConfigData::Toml(old), ConfigData::Json(new)) => {
+                diff_formats(table_entries(old), new.children(), &mut diffs)
+            }
+            (ConfigData::Json(old), ConfigData::Toml(new)) => {
+                diff_formats(old.children(), table_entries(new), &mut diffs)
+            }
+        }
+
+        diffs
+    }
+
+    // Scalar values of the config by dotted key ("database.port"), array
+    // elements are keyed by index ("servers.0.host"). Strings are kept as
+    // they are, other scalars are written in the format of the config;
+    // empty tables, empty arrays and json nulls have no entries.
+    pub fn flatten(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+
+        match &self.data {
+            ConfigData::Toml(config) => {
+                flatten_entries("", table_entries(config), &mut entries)
+            }
+            ConfigData::Json(config) => {
+                flatten_entries("", config.children(), &mut entries)
+            }
+        }
+
+        entries
+    }
+}
+
+// Normalize a key for style-insensitive comparison
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/* ------------------------------ */
+/* Interpolation of config values */
+/* ------------------------------ */
+
+// Common interface of the toml and json value trees
+trait ValueTree: Sized {
+    // Entry of a table (object) by key, or of an array by index
+    fn child(&self, key: &str) -> Option<&Self>;
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut Self>;
+
+    // Entries of a table (object) or an array
+    fn children(&self) -> Vec<(String, &Self)>;
+
+    fn as_string(&self) -> Option<&str>;
+
+    // String form of non-string scalar values
+    fn scalar_string(&self) -> Option<String>;
+
+    fn set_string(&mut self, value: String);
+
+    fn is_table(&self) -> bool;
+
+    fn empty_table() -> Self;
+
+    // Insert an entry into a table (object), ignored for other values
+    fn insert_child(&mut self, key: &str, value: Self);
+}
+
+impl ValueTree for toml::Value {
+    fn child(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Table(table) => table.get(key),
+            Self::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut Self> {
+        match self {
+            Self::Table(table) => table.get_mut(key),
+            Self::Array(array) => array.get_mut(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    fn children(&self) -> Vec<(String, &Self)> {
+        match self {
+            Self::Table(table) => table
+                .iter()
+                .map(|(key, value)| (key.clone(), value))
+                .collect(),
+            Self::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (i.to_string(), value))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn as_string(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn scalar_string(&self) -> Option<String> {
+        match self {
+            Self::Integer(value) => Some(value.to_string()),
+            Self::Float(value) => Some(value.to_string()),
+            Self::Boolean(value) => Some(value.to_string()),
+            Self::Datetime(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn set_string(&mut self, value: String) {
+        *self = Self::String(value);
+    }
+
+    fn is_table(&self) -> bool {
+        matches!(self, Self::Table(_))
+    }
+
+    fn empty_table() -> Self {
+        Self::Table(toml::Table::new())
+    }
+
+    fn insert_child(&mut self, key: &str, value: Self) {
+        if let Self::Table(table) = self {
+            table.insert(key.to_string(), value);
+        }
+    }
+}
+
+impl ValueTree for serde_json::Value {
+    fn child(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(object) => object.get(key),
+            Self::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut Self> {
+        match self {
+            Self::Object(object) => object.get_mut(key),
+            Self::Array(array) => array.get_mut(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    fn children(&self) -> Vec<(String, &Self)> {
+        match self {
+            Self::Object(object) => object
+                .iter()
+                .map(|(key, value)| (key.clone(), value))
+                .collect(),
+            Self::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (i.to_string(), value))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn as_string(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn scalar_string(&self) -> Option<String> {
+        match self {
+            Self::Number(value) => Some(value.to_string()),
+            Self::Bool(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn set_string(&mut self, value: String) {
+        *self = Self::String(value);
+    }
+
+    fn is_table(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+
+    fn empty_table() -> Self {
+        Self::Object(serde_json::Map::new())
+    }
+
+    fn insert_child(&mut self, key: &str, value: Self) {
+        if let Self::Object(object) = self {
+            object.insert(key.to_string(), value);
+        }
+    }
+}
+
+// Replace all references in the string values of the tree
+fn interpolate_tree<V: ValueTree>(root: &mut V) -> Result<(), Error> {
+    let mut paths = Vec::new();
+    collect_string_paths(root, &mut Vec::new(), &mut paths);
+
+    // Resolve everything before modifying the tree
+    let mut resolved = HashMap::new();
+    for path in &paths {
+        resolve_value(root, path, &mut resolved, &mut Vec::new())?;
+    }
+
+    for path in paths {
+        let mut value = &mut *root;
+        for key in &path {
+            value = value.child_mut(key).expect("Path was collected");
+        }
+        value.set_string(resolved.remove(&path).expect("Path was resolved"));
+    }
+
+    Ok(())
+}
+
+// Collect the paths to all string values of the tree
+fn collect_string_paths<V: ValueTree>(
+    value: &V,
+    path: &mut Vec<String>,
+    paths: &mut Vec<Vec<String>>,
+) {
+    if value.as_string().is_some() {
+        paths.push(path.clone());
+    }
+
+    for (key, child) in value.children() {
+        path.push(key);
+        collect_string_paths(child, path, paths);
+        path.pop();
+    }
+}
+
+// Resolve the value at the path, following the references recursively
+fn resolve_value<V: ValueTree>(
+    root: &V,
+    path: &[String],
+    resolved: &mut HashMap<Vec<String>, String>,
+    stack: &mut Vec<Vec<String>>,
+) -> Result<String, Error> {
+    if let Some(value) = resolved.get(path) {
+        return Ok(value.clone());
+    }
+
+    let key = path.join(".");
+    let interpolation_error = |reason: String| Error::Interpolation {
+        key: key.clone(),
+        reason,
+    };
+
+    // Detect cycles among the references
+    if stack.iter().any(|visited| visited == path) {
+        let cycle: Vec<String> = stack
+            .iter()
+            .skip_while(|visited| visited.as_slice() != path)
+            .chain([&path.to_vec()])
+            .map(|visited| visited.join("."))
+            .collect();
+        return Err(interpolation_error(format!(
+            "cyclic reference {}",
+            cycle.join(" -> ")
+        )));
+    }
+
+    let value = path
+        .iter()
+        .try_fold(root, |value, key| value.child(key))
+        .ok_or_else(|| interpolation_error(String::from("key not found")))?;
+
+    let raw = match value.as_string() {
+        Some(raw) => raw,
+        None => {
+            return value.scalar_string().ok_or_else(|| {
+                interpolation_error(String::from(
+                    "only scalar values can be referenced",
+                ))
+            })
+        }
+    };
+
+    stack.push(path.to_vec());
+
+    let mut interpolated = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| {
+            interpolation_error(String::from("unterminated ${ reference"))
+        })?;
+
+        let reference: Vec<String> =
+            after[..end].split('.').map(str::to_string).collect();
+
+        if reference
+            .iter()
+            .try_fold(root, |value, key| value.child(key))
+            .is_none()
+        {
+            return Err(interpolation_error(format!(
+                "unknown reference ${{{}}}",
+                &after[..end]
+            )));
+        }
+        interpolated
+            .push_str(&resolve_value(root, &reference, resolved, stack)?);
+
+        rest = &after[end + 1..];
+    }
+    interpolated.push_str(rest);
+
+    stack.pop();
+    resolved.insert(path.to_vec(), interpolated.clone());
+
+    Ok(interpolated)
+}
+
+/* --------------- */
+/* Deprecated keys */
+/* --------------- */
+
+// Entry of the toml config at the dotted key
+fn lookup_toml<'a>(
+    config: &'a toml::Table,
+    key: &str,
+) -> Option<&'a toml::Value> {
+    match key.split_once('.') {
+        Some((first, rest)) => lookup_tree(config.get(first)?, rest),
+        None => config.get(key),
+    }
+}
+
+// Entry at the dotted key
+fn lookup_tree<'a, V: ValueTree>(root: &'a V, key: &str) -> Option<&'a V> {
+    key.split('.').try_fold(root, |value, key| value.child(key))
+}
+
+// Copy the entry at the old dotted key to the new one (if it is not set)
+fn migrate_tree<V: ValueTree + Clone>(
+    root: &mut V,
+    old: &str,
+    new: &str,
+) -> bool {
+    if lookup_tree(root, new).is_some() {
+        return false;
+    }
+    let value = match lookup_tree(root, old) {
+        Some(value) => value.clone(),
+        None => return false,
+    };
+
+    let (parents, last) = match new.rsplit_once('.') {
+        Some((parents, last)) => (parents.split('.').collect(), last),
+        None => (Vec::new(), new),
+    };
+
+    let mut table = root;
+    for key in parents {
+        if table.child(key).is_none() {
+            table.insert_child(key, V::empty_table());
+        }
+        table = match table.child_mut(key) {
+            Some(child) if child.is_table() => child,
+            _ => return false,
+        };
+    }
+
+    table.insert_child(last, value);
+    true
+}
+
+/* --------------- */
+/* Merging configs */
+/* --------------- */
+
+fn merge_toml(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_toml(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (
+            serde_json::Value::Object(base),
+            serde_json::Value::Object(overlay),
+        ) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base) => merge_json(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/* ------------------------------ */
+/* Conversion between the formats */
+/* ------------------------------ */
+
+// Convert a toml value (at the dotted key) into a json value
+fn toml_to_json(
+    key: &str,
+    value: &toml::Value,
+) -> Result<serde_json::Value, Error> {
+    let conversion_error = |reason: &str| Error::Conversion {
+        key: key.to_string(),
+        reason: reason.to_string(),
+    };
+
+    Ok(match value {
+        toml::Value::String(value) => serde_json::Value::from(value.clone()),
+        toml::Value::Integer(value) => serde_json::Value::from(*value),
+        toml::Value::Float(value) => serde_json::Number::from_f64(*value)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| conversion_error("JSON has no NaN or infinity"))?,
+        toml::Value::Boolean(value) => serde_json::Value::from(*value),
+        toml::Value::Datetime(_) => {
+            return Err(conversion_error("JSON has no datetime type"))
+        }
+        toml::Value::Array(array) => serde_json::Value::Array(
+            array
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    toml_to_json(&dotted_key(key, &i.to_string()), value)
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .iter()
+                .map(|(name, value)| {
+                    Ok((
+                        name.clone(),
+                        toml_to_json(&dotted_key(key, name), value)?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
+        ),
+    })
+}
+
+// Convert a json value (at the dotted key) into a toml value
+fn json_to_toml(
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<toml::Value, Error> {
+    let conversion_error = |reason: &str| Error::Conversion {
+        key: key.to_string(),
+        reason: reason.to_string(),
+    };
+
+    Ok(match value {
+        serde_json::Value::Null => {
+            return Err(conversion_error("TOML has no null value"))
+        }
+        serde_json::Value::Bool(value) => toml::Value::from(*value),
+        serde_json::Value::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                toml::Value::from(value)
+            } else if number.is_u64() {
+                return Err(conversion_error(
+                    "integer does not fit into the 64-bit TOML integer",
+                ));
+            } else {
+                toml::Value::from(number.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        serde_json::Value::String(value) => toml::Value::from(value.clone()),
+        serde_json::Value::Array(array) => toml::Value::Array(
+            array
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    json_to_toml(&dotted_key(key, &i.to_string()), value)
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_json::Value::Object(object) => toml::Value::Table(
+            object
+                .iter()
+                .map(|(name, value)| {
+                    Ok((
+                        name.clone(),
+                        json_to_toml(&dotted_key(key, name), value)?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
+        ),
+    })
+}
+
+/* --------------------------- */
+/* Differences between configs */
+/* --------------------------- */
+
+// Entries of a toml table in the form used by ValueTree::children
+fn table_entries(table: &toml::Table) -> Vec<(String, &toml::Value)> {
+    table
+        .iter()
+        .map(|(key, value)| (key.clone(), value))
+        .collect()
+}
+
+// Join the key of a nested entry to the dotted path of its parent
+fn dotted_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+// Compare the entries of two tables of the same format
+fn diff_entries<'a, V: ValueTree + PartialEq>(
+    prefix: &str,
+    old: Vec<(String, &'a V)>,
+    new: Vec<(String, &'a V)>,
+    diffs: &mut Vec<ConfigDiff<'a>>,
+) where
+    &'a V: Into<ConfigValue<'a>>,
+{
+    let new_map: HashMap<&str, &'a V> = new
+        .iter()
+        .map(|(key, value)| (key.as_str(), *value))
+        .collect();
+    let old_map: HashMap<&str, &'a V> = old
+        .iter()
+        .map(|(key, value)| (key.as_str(), *value))
+        .collect();
+
+    for (key, old_value) in &old {
+        let key_path = dotted_key(prefix, key);
+
+        match new_map.get(key.as_str()) {
+            None => diffs.push(ConfigDiff::Removed {
+                key: key_path,
+                value: (*old_value).into(),
+            }),
+            Some(new_value) if old_value.is_table() && new_value.is_table() => {
+                diff_entries(
+                    &key_path,
+                    old_value.children(),
+                    new_value.children(),
+                    diffs,
+                )
+            }
+            Some(new_value) if old_value != new_value => {
+                diffs.push(ConfigDiff::Changed {
+                    key: key_path,
+                    old: (*old_value).into(),
+                    new: (*new_value).into(),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+
+    for (key, new_value) in &new {
+        if !old_map.contains_key(key.as_str()) {
+            diffs.push(ConfigDiff::Added {
+                key: dotted_key(prefix, key),
+                value: (*new_value).into(),
+            });
+        }
+    }
+}
+
+// Compare the top-level entries of configs of different formats
+fn diff_formats<'a, O: ValueTree, N: ValueTree>(
+    old: Vec<(String, &'a O)>,
+    new: Vec<(String, &'a N)>,
+    diffs: &mut Vec<ConfigDiff<'a>>,
+) where
+    &'a O: Into<ConfigValue<'a>>,
+    &'a N: Into<ConfigValue<'a>>,
+{
+    let new_map: HashMap<&str, &'a N> = new
+        .iter()
+        .map(|(key, value)| (key.as_str(), *value))
+        .collect();
+    let old_map: HashMap<&str, &'a O> = old
+        .iter()
+        .map(|(key, value)| (key.as_str(), *value))
+        .collect();
+
+    for (key, old_value) in &old {
+        match new_map.get(key.as_str()) {
+            None => diffs.push(ConfigDiff::Removed {
+                key: key.clone(),
+                value: (*old_value).into(),
+            }),
+            Some(new_value) => diffs.push(ConfigDiff::Changed {
+                key: key.clone(),
+                old: (*old_value).into(),
+                new: (*new_value).into(),
+            }),
+        }
+    }
+
+    for (key, new_value) in &new {
+        if !old_map.contains_key(key.as_str()) {
+            diffs.push(ConfigDiff::Added {
+                key: key.clone(),
+                value: (*new_value).into(),
+            });
+        }
+    }
+}
+
+/* ---------------- */
+/* Flattened config */
+/* ---------------- */
+
+// Insert the scalar values below the entries under their dotted keys
+fn flatten_entries<V: ValueTree>(
+    prefix: &str,
+    children: Vec<(String, &V)>,
+    entries: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in children {
+        let key = dotted_key(prefix, &key);
+
+        match value.as_string() {
+            Some(string) => {
+                entries.insert(key, string.to_string());
+            }
+            None => match value.scalar_string() {
+                Some(scalar) => {
+                    entries.insert(key, scalar);
+                }
+                None => flatten_entries(&key, value.children(), entries),
+            },
+        }
+    }
+}
+
+/* ---------------- */
+/* Config overrides */
+/* ---------------- */
+
+// Toml config of the values by dotted key
+fn overrides_config(entries: &[(String, String)]) -> Config {
+    let mut table = toml::Table::new();
+
+    for (key, value) in entries {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let last = segments.pop().unwrap_or_default();
+
+        let mut parent = &mut table;
+        for segment in segments {
+            let child = parent
+                .entry(segment)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !child.is_table() {
+                *child = toml::Value::Table(toml::Table::new());
+            }
+            parent = child.as_table_mut().expect("Entry is a table");
+        }
+        parent.insert(last.to_string(), override_value(value));
+    }
+
+    Config::from(table)
+}
+
+// Read the value as a toml value, falling back to the string itself
+fn override_value(value: &str) -> toml::Value {
+    format!("value = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::from(value))
+}
+
+pub fn load_config(filename: &Path) -> Config {
+    try_load_config(filename).unwrap_or_else(|e| panic!("{e}"))
+}
+
+// Fallible version of load_config
+pub fn try_load_config(filename: &Path) -> Result<Config, Error> {
+    let parse_error = |reason: String| Error::Parse {
+        path: filename.to_path_buf(),
+        reason,
+    };
+
+    let extension = filename
+        .extension()
+        .ok_or_else(|| parse_error(String::from("no extension found")))?;
+
+    let mut config = match extension.to_str() {
+        Some("toml") => load_toml(filename),
+        Some("json") => load_json(filename),
+        #[cfg(feature = "json5")]
+        Some("json5") => load_json5(filename),
+        _ => Err(parse_error(format!(
+            "config files with .{} extension are not supported",
+            extension.to_string_lossy()
+        ))),
+    }?;
+
+    config.set_source_path(filename);
+    Ok(config)
+}
+
+// Load a base config (e.g. config.toml) and overlay the environment specific
+// config next to it (config.<env>.toml) if it exists
+pub fn load_config_layered(base: &Path, env: &str) -> Result<Config, Error> {
+    let mut config = try_load_config(base)?;
+
+    let mut env_name = base.file_stem().unwrap_or_default().to_owned();
+    env_name.push(format!(".{env}"));
+    if let Some(extension) = base.extension() {
+        env_name.push(".");
+        env_name.push(extension);
+    }
+
+    let env_path = base.with_file_name(env_name);
+    if env_path.exists() {
+        config.merge(&try_load_config(&env_path)?)?;
+    }
+    Ok(config)
+}
+
+// Method to deserialize a config into the target structure
+pub trait FromConfig: for<'a> Deserialize<'a> {
+    fn from_config(config: &Config, table_name: &str) -> Self {
+        Self::try_from_config(config, table_name)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // Fallible version of from_config
+    fn try_from_config(
+        config: &Config,
+        table_name: &str,
+    ) -> Result<Self, Error> {
+        deserialize_entry(config, table_name)
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> FromConfig for T {}
+
+// Config target that lives in a fixed table of the config, implemented with
+// #[config_table("name")] of the derive feature (or by hand)
+pub trait ConfigTable: FromConfig {
+    const TABLE_NAME: &'static str;
+
+    // Deserialize the target from its table (named apart from
+    // TargetFromBuilder::from_config_default, a type may implement both)
+    fn from_config_table(config: &Config) -> Self {
+        Self::from_config(config, Self::TABLE_NAME)
+    }
+
+    // Fallible version of from_config_table
+    fn try_from_config_table(config: &Config) -> Result<Self, Error> {
+        Self::try_from_config(config, Self::TABLE_NAME)
+    }
+}
+
+// Deserialize an optional table, falling back to T::default() if it is
+// absent. Missing fields of a present table are filled in only if the target
+// is annotated with #[serde(default)] (on the structure or on the fields).
+pub fn from_config_or_default<T: FromConfig + Default>(
+    config: &Config,
+    table_name: &str,
+) -> T {
+    if config.contains_key(table_name) {
+        T::from_config(config, table_name)
+    } else {
+        T::default()
+    }
+}
+
+// Deserialize an array of tables (objects) into a vector of structures
+pub fn from_config_seq<T: FromConfig>(
+    config: &Config,
+    key: &str,
+) -> Result<Vec<T>, Error> {
+    deserialize_entry(config, key)
+}
+
+// Deserialize a structure from the root of the config, e.g. a target whose
+// fields are spread over the top level. Fields marked #[serde(flatten)]
+// collect the keys left over by the other fields, so a sub-structure may be
+// inlined in the top level (or in a table with from_config). The keys of a
+// flattened target are not checked by from_config_strict
+pub fn from_config_root<T: FromConfig>(config: &Config) -> Result<T, Error> {
+    let deserialize_error = |reason: String| Error::Deserialize {
+        table_name: String::from("<root>"),
+        reason,
+        source_path: config.source_path.clone(),
+    };
+
+    let _source = SourcePathGuard::enter(config);
+    match &config.data {
+        ConfigData::Toml(table) => {
+            toml::Value::Table(table.clone()).try_into().map_err(|e| {
+                let reason = e.to_string();
+                deserialize_error(
+                    reason.split_whitespace().collect::<Vec<_>>().join(" "),
+                )
+            })
+        }
+        ConfigData::Json(value) => serde_json::from_value(value.clone())
+            .map_err(|e| deserialize_error(e.to_string())),
+    }
+}
+
+// Deserialize a structure from a table with the keys of a shared table
+// (e.g. [common]) added to it, the keys of the table overriding the shared
+// ones. Meant for several targets sharing a structure through
+// #[serde(flatten)] common: Common, an absent shared table adds nothing
+// while an absent table is an error as with from_config
+pub fn from_config_with_common<T: FromConfig>(
+    config: &Config,
+    table_name: &str,
+    common_name: &str,
+) -> Result<T, Error> {
+    let data = match &config.data {
+        ConfigData::Toml(root) => {
+            let mut table = match root.get(common_name) {
+                Some(toml::Value::Table(common)) => common.clone(),
+                _ => toml::Table::new(),
+            };
+            match root.get(table_name) {
+                Some(toml::Value::Table(overlay)) => {
+                    merge_toml(&mut table, overlay.clone())
+                }
+                _ => return deserialize_entry(config, table_name),
+            }
+            ConfigData::Toml(toml::Table::from_iter([(
+                table_name.to_string(),
+                toml::Value::Table(table),
+            )]))
+        }
+        ConfigData::Json(root) => {
+            let mut object = match root.get(common_name) {
+                Some(common @ serde_json::Value::Object(_)) => common.clone(),
+                _ => serde_json::Value::Object(serde_json::Map::new()),
+            };
+            match root.get(table_name) {
+                Some(overlay @ serde_json::Value::Object(_)) => {
+                    merge_json(&mut object, overlay.clone())
+                }
+                _ => return deserialize_entry(config, table_name),
+            }
+            ConfigData::Json(serde_json::json!({ table_name: object }))
+        }
+    };
+
+    deserialize_entry(
+        &Config {
+            data,
+            source_path: config.source_path.clone(),
+        },
+        table_name,
+    )
+}
+
+// Deserialize a structure from a table, rejecting the keys the structure
+// does not expect (like #[serde(deny_unknown_fields)], but all unknown keys
+// are listed in the error)
+pub fn from_config_strict<T: FromConfig>(
+    config: &Config,
+    table_name: &str,
+) -> Result<T, Error> {
+    let mut fields = None;
+    // The probe always fails after recording the fields
+    let _ = T::deserialize(FieldProbe(&mut fields));
+
+    if let Some(fields) = fields {
+        let keys: Vec<String> = match &config.data {
+            ConfigData::Toml(config) => match config.get(table_name) {
+                Some(toml::Value::Table(table)) => {
+                    table.keys().cloned().collect()
+                }
+                _ => Vec::new(),
+            },
+            ConfigData::Json(config) => match config.get(table_name) {
+                Some(serde_json::Value::Object(object)) => {
+                    object.keys().cloned().collect()
+                }
+                _ => Vec::new(),
+            },
+        };
+
+        let quote = |keys: &[&str]| {
+            keys.iter()
+                .map(|key| format!("`{key}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let unknown: Vec<&str> = keys
+            .iter()
+            .map(String::as_str)
+            .filter(|key| !fields.contains(key))
+            .collect();
+
+        if !unknown.is_empty() {
+            return Err(Error::Deserialize {
+                table_name: table_name.to_string(),
+                reason: format!(
+                    "unknown field(s) {}, expected {}",
+                    quote(&unknown),
+                    quote(fields)
+                ),
+                source_path: config.source_path.clone(),
+            });
+        }
+    }
+
+    deserialize_entry(config, table_name)
+}
+
+// Deserializer recording the field names of the deserialized structure
+struct FieldProbe<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> serde::Deserializer<'de> for FieldProbe<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("not a structure"))
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(serde::de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+// Deserialize an internally tagged enum (#[serde(tag = "...")]) from a
+// table, the errors name the tag value of the table (or its absence) in
+// addition to the reason given by serde (e.g. the list of known variants)
+pub fn from_config_tagged<T: FromConfig>(
+    config: &Config,
+    table_name: &str,
+    tag: &str,
+) -> Result<T, Error> {
+    deserialize_entry(config, table_name).map_err(|error| match error {
+        Error::Deserialize {
+            table_name,
+            reason,
+            source_path,
+        } => {
+            let tag_value = match &config.data {
+                ConfigData::Toml(config) => config
+                    .get(&table_name)
+                    .and_then(|table| table.get(tag))
+                    .map(|value| value.to_string()),
+                ConfigData::Json(config) => config
+                    .get(&table_name)
+                    .and_then(|table| table.get(tag))
+                    .map(|value| value.to_string()),
+            };
+
+            let reason = match tag_value {
+                Some(value) => format!("{tag} = {value}: {reason}"),
+                None => format!("no {tag:?} tag selecting the variant"),
+            };
+            Error::Deserialize {
+                table_name,
+                reason,
+                source_path,
+            }
+        }
+        error => error,
+    })
+}
+
+// Deserialize a top-level entry of the config, the errors mention the file
+// the config was loaded from
+fn deserialize_entry<T: for<'a> Deserialize<'a>>(
+    config: &Config,
+    key: &str,
+) -> Result<T, Error> {
+    let deserialize_error = |reason: String| Error::Deserialize {
+        table_name: key.to_string(),
+        reason,
+        source_path: config.source_path.clone(),
+    };
+    let missing_error = || Error::MissingKeys {
+        keys: vec![key.to_string()],
+        source_path: config.source_path.clone(),
+    };
+
+    let _source = SourcePathGuard::enter(config);
+    match &config.data {
+        ConfigData::Toml(config) => match config.get(key) {
+            // toml appends the path of the field on separate lines
+            Some(value) => value.clone().try_into().map_err(|e| {
+                let reason = e.to_string();
+                deserialize_error(
+                    reason.split_whitespace().collect::<Vec<_>>().join(" "),
+                )
+            }),
+            None => Err(missing_error()),
+        },
+        ConfigData::Json(config) => match config.get(key) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| deserialize_error(e.to_string())),
+            None => Err(missing_error()),
+        },
+    }
+}
+
+thread_local! {
+    // File of the config being deserialized on the thread
+    static SOURCE_PATH: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+// Returns the file of the config being deserialized on the thread, for
+// targets resolving their paths against it (e.g. FileManager with
+// resolve_relative_to_config), None outside of the deserialization or for
+// configs not loaded from a file
+pub(crate) fn deserialized_source_path() -> Option<PathBuf> {
+    SOURCE_PATH.with(|source_path| source_path.borrow().clone())
+}
+
+// Sets the file of the config being deserialized until dropped, restoring
+// the previous one (deserialization may be nested)
+struct SourcePathGuard(Option<PathBuf>);
+
+impl SourcePathGuard {
+    fn enter(config: &Config) -> Self {
+        SourcePathGuard(SOURCE_PATH.with(|source_path| {
+            source_path.replace(config.source_path.clone())
+        }))
+    }
+}
+
+impl Drop for SourcePathGuard {
+    fn drop(&mut self) {
+        SOURCE_PATH.with(|source_path| source_path.replace(self.0.take()));
+    }
+}
+
+/* ---------------------------------------- */
+/* Method for loading data from .toml files */
+/* ---------------------------------------- */
+
+// Open a config.toml file and save the data as a toml::Value
+fn load_toml(filename: &Path) -> Result<Config, Error> {
+    // Read the contents of the file
+    let contents = fs::read_to_string(filename)?;
+
+    // Save the data to toml::Table
+    match contents.parse::<toml::Table>() {
+        Ok(table) => Ok(Config::from(ConfigData::Toml(table))),
+        Err(e) => Err(Error::Parse {
+            path: filename.to_path_buf(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/* ---------------------------------------- */
+/* Method for loading data from .json files */
+/* ---------------------------------------- */
+
+// Open a config.toml file and save the data as a toml::Value
+fn load_json(filename: &Path) -> Result<Config, Error> {
+    // Read the contents of the file
+    let contents = fs::read_to_string(filename)?;
+
+    // Save the data to serde_json::Value
+    match parse_json(&contents) {
+        Ok(value) => Ok(Config::from(ConfigData::Json(value))),
+        Err(e) => Err(Error::Parse {
+            path: filename.to_path_buf(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+// Parse JSON contents, blank contents (e.g. an empty file) are an empty
+// object as in TOML
+fn parse_json(contents: &str) -> serde_json::Result<serde_json::Value> {
+    if contents.trim().is_empty() {
+        Ok(serde_json::Value::Object(serde_json::Map::new()))
+    } else {
+        serde_json::from_str(contents)
+    }
+}
+
+/* ----------------------------------------- */
+/* Method for loading data from .json5 files */
+/* ----------------------------------------- */
+
+// Parse json5 contents, blank contents are an empty object as in JSON
+#[cfg(feature = "json5")]
+fn parse_json5(contents: &str) -> Result<serde_json::Value, json5::Error> {
+    if contents.trim().is_empty() {
+        Ok(serde_json::Value::Object(serde_json::Map::new()))
+    } else {
+        json5::from_str(contents)
+    }
+}
+
+// Open a config.json5 file and save the data as a serde_json::Value
+#[cfg(feature = "json5")]
+fn load_json5(filename: &Path) -> Result<Config, Error> {
+    // Read the contents of the file
+    let contents = fs::read_to_string(filename)?;
+
+    match parse_json5(&contents) {
+        Ok(value) => Ok(Config::from(ConfigData::Json(value))),
+        Err(e) => Err(Error::Parse {
+            path: filename.to_path_buf(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        from_config_or_default, from_config_root, from_config_seq,
+        from_config_strict, from_config_tagged, from_config_with_common,
+        load_config, load_config_layered, try_load_config, BTreeMap, Config,
+        ConfigBuilder, ConfigData, ConfigDiff, ConfigResolver, ConfigTable,
+        ConfigValue, Deserialize, Error, Format, FromConfig, Path,
+        SharedConfig,
+    };
+
+    use std::io::Write;
+
+    use crate::files::OverwriteType;
+
+    use crate::testing::TempProject;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct TestStruct {
+        x: u32,
+        y: u32,
+        z: u32,
+    }
+
+    fn touch(path: &Path) {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+    }
+
+    fn rm(path: &Path) {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn shared_config() {
+        let config = Config::from_toml_str("[table]\nx = 1\ny = 2\nz = 3\n");
+        let shared: SharedConfig = config.into_shared();
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    assert!(shared.contains_key("table"));
+                    TestStruct::from_config(&shared, "table")
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            assert_eq!(TestStruct { x: 1, y: 2, z: 3 }, worker.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn layered_config() {
+        let temp = TempProject::new("toml", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path();
+        let base = dir.join("config.toml");
+        std::fs::write(
+            &base,
+            "[run]\nsteps = 100\nlabel = \"base\"\n[output]\npath = \"out\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("config.prod.toml"), "[run]\nsteps = 5000\n")
+            .unwrap();
+
+        // Nested tables are merged key by key
+        let config = load_config_layered(&base, "prod").unwrap();
+        let expected = Config::from_toml_str(
+            "[run]\nsteps = 5000\nlabel = \"base\"\n[output]\npath = \"out\"\n",
+        );
+        assert!(config.diff(&expected).is_empty());
+        assert_eq!(Some(base.as_path()), config.source_path());
+
+        // The base stands alone without the environment file
+        let config = load_config_layered(&base, "dev").unwrap();
+        assert!(config.diff(&load_config(&base)).is_empty());
+
+        // Overlays in the other format are converted
+        let mut config = Config::from_json_str(r#"{"run": {"steps": 1}}"#);
+        config
+            .merge(&Config::from_toml_str("[run]\nlabel = \"json\"\n"))
+            .unwrap();
+        let expected =
+            Config::from_json_str(r#"{"run": {"steps": 1, "label": "json"}}"#);
+        assert!(config.diff(&expected).is_empty());
+    }
+
+    #[test]
+    fn config_dir() {
+        let temp = TempProject::new("toml", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(
+            dir.join("10-base.toml"),
+            "[run]\nsteps = 1\nlabel = \"base\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("20-override.json"),
+            r#"{"run": {"steps": 2}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("30-extra.toml"), "[output]\npath = \"out\"\n")
+            .unwrap();
+        std::fs::write(dir.join("README.md"), "not a config").unwrap();
+        std::fs::write(dir.join("nested/ignored.toml"), "ignored = true")
+            .unwrap();
+
+        let config = Config::from_dir(dir).unwrap();
+        let expected = Config::from_toml_str(
+            "[run]\nsteps = 2\nlabel = \"base\"\n[output]\npath = \"out\"\n",
+        );
+        assert!(config.diff(&expected).is_empty());
+        assert_eq!(Some(dir), config.source_path());
+
+        assert!(Config::from_dir(&dir.join("nested/ignored.toml")).is_err());
+        assert!(Config::from_dir(&dir.join("nested"))
+            .unwrap()
+            .contains_key("ignored"));
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn watch_config() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let temp = TempProject::new("toml", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[run]\nsteps = 1\n").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let watcher = Config::watch(&path, move |config| {
+            sender.send(config.flatten()["run.steps"].clone()).unwrap();
+        })
+        .unwrap();
+        let timeout = Duration::from_secs(10);
+
+        // Rapid successive writes are reloaded once
+        for steps in 2..5 {
+            std::fs::write(&path, format!("[run]\nsteps = {steps}\n")).unwrap();
+        }
+        assert_eq!("4", receiver.recv_timeout(timeout).unwrap());
+
+        // An invalid state is skipped
+        std::fs::write(&path, "[run\nsteps =").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_err());
+        std::fs::write(&path, "[run]\nsteps = 5\n").unwrap();
+        assert_eq!("5", receiver.recv_timeout(timeout).unwrap());
+
+        // Other files of the directory are not watched
+        std::fs::write(dir.join("other.toml"), "x = 1").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_err());
+
+        drop(watcher);
+    }
+
+    #[test]
+    fn embedded_config() {
+        const EMBEDDED: &[u8] = b"[table]\nx = 1\ny = 2\nz = 3\n";
+
+        let config = Config::from_embedded(EMBEDDED, Format::Toml).unwrap();
+        assert_eq!(
+            TestStruct { x: 1, y: 2, z: 3 },
+            TestStruct::from_config(&config, "table")
+        );
+        let config =
+            Config::from_embedded(br#"{"table": {"x": 1}}"#, Format::Json)
+                .unwrap();
+        assert!(config.contains_key("table"));
+
+        for (bytes, format) in [
+            (EMBEDDED, Format::Json),
+            (b"x = ".as_slice(), Format::Toml),
+            (b"\xff".as_slice(), Format::Toml),
+        ] {
+            match Config::from_embedded(bytes, format) {
+                Err(Error::ParseStr {
+                    format: reported, ..
+                }) => {
+                    assert_eq!(format, reported)
+                }
+                _ => panic!("Invalid config was parsed"),
+            }
+        }
+
+        assert!(Config::try_from_toml_str("[table").is_err());
+        assert!(Config::try_from_json_str("{").is_err());
+    }
+
+    #[test]
+    fn in_memory_config() {
+        let mut table = toml::Table::new();
+        table.insert(String::from("x"), toml::Value::from(1));
+        table.insert(String::from("y"), toml::Value::from(2));
+        table.insert(String::from("z"), toml::Value::from(3));
+
+        let expected = TestStruct { x: 1, y: 2, z: 3 };
+
+        let config = Config::from_pairs(&[
+            ("table", toml::Value::Table(table.clone())),
+            ("other", toml::Value::from(true)),
+        ]);
+        assert_eq!(expected, TestStruct::from_config(&config, "table"));
+
+        let config = ConfigBuilder::new()
+            .set("table", table)
+            .set("other", true)
+            .build();
+        assert_eq!(expected, TestStruct::from_config(&config, "table"));
+        assert!(config.require_keys(&["table", "other"]).is_ok());
+    }
+
+    #[test]
+    fn source_path_in_errors() {
+        let temp = TempProject::new("toml", OverwriteType::Overwrite).unwrap();
+        let path = temp.path().join("source_path_in_errors.toml");
+        let path = path.as_path();
+        std::fs::write(path, "[data]\nx = 1\n").unwrap();
+
+        let config = load_config(path);
+        assert_eq!(Some(path), config.source_path());
+
+        let error = TestStruct::try_from_config(&config, "data").unwrap_err();
+        assert!(error.to_string().starts_with(&format!(
+            "Failed to parse [data] in {}",
+            path.display()
+        )));
+
+        let error = TestStruct::try_from_config(&config, "other").unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with(&format!("in {}", path.display())));
+
+        // Configs created from strings have no source
+        let config = Config::from_toml_str("[data]\nx = 1\n");
+        assert_eq!(None, config.source_path());
+        let error = TestStruct::try_from_config(&config, "data").unwrap_err();
+        assert!(error.to_string().starts_with("Failed to parse [data]: "));
+    }
+
+    #[test]
+    fn try_load_errors() {
+        assert!(matches!(
+            try_load_config(Path::new("file_with_wrong_extension.dat")),
+            Err(Error::Parse { .. })
+        ));
+        assert!(matches!(
+            try_load_config(Path::new("this_file_doesnt_exist.toml")),
+            Err(Error::Io(_))
+        ));
+
+        let config = Config::from_toml_str("[table]\nx = 1\n");
+        assert!(matches!(
+            TestStruct::try_from_config(&config, "missing"),
+            Err(Error::MissingKeys { .. })
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_extension() {
+        let path = Path::new("file_with_wrong_extension.dat");
+        load_config(path);
+    }
+
+    #[test]
+    fn require_keys() {
+        let configs = [
+            Config::from_toml_str(
+                r#"
+            [data]
+            x = 1
+
+            [output]
+            path = "."
+            "#,
+            ),
+            Config::from_json_str(
+                r#"{"data": {"x": 1}, "output": {"path": "."}}"#,
+            ),
+        ];
+
+        for config in configs {
+            assert!(config.require_keys(&["data", "output"]).is_ok());
+
+            match config.require_keys(&["data", "database", "server"]) {
+                Err(Error::MissingKeys { keys, .. }) => {
+                    assert_eq!(vec!["database", "server"], keys)
+                }
+                _ => panic!("Missing keys were not reported"),
+            }
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let configs = [
+            Config::from_toml_str("[plugin_b]\n[plugin_a]\n[other]\n"),
+            Config::from_json_str(
+                r#"{"plugin_b": {}, "plugin_a": {}, "other": {}}"#,
+            ),
+        ];
+
+        for config in configs {
+            if cfg!(feature = "preserve_order") {
+                assert_eq!(
+                    vec!["plugin_b", "plugin_a", "other"],
+                    config.keys()
+                );
+            } else {
+                assert_eq!(
+                    vec!["other", "plugin_a", "plugin_b"],
+                    config.keys()
+                );
+            }
+
+            assert!(config.contains_key("plugin_a"));
+            assert!(!config.contains_key("plugin_c"));
+        }
+
+        assert!(Config::from_json_str("[1, 2]").keys().is_empty());
+    }
+
+    #[test]
+    fn flatten() {
+        let expected: BTreeMap<String, String> = [
+            ("database.host", "x"),
+            ("database.port", "5432"),
+            ("debug", "true"),
+            ("servers.0.host", "a"),
+            ("servers.1.host", "b"),
+            ("servers.1.ports.0", "80"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let config = Config::from_toml_str(
+            r#"
+            debug = true
+            empty = []
+
+            [database]
+            host = "x"
+            port = 5432
+
+            [[servers]]
+            host = "a"
+
+            [[servers]]
+            host = "b"
+            ports = [80]
+            "#,
+        );
+        assert_eq!(expected, config.flatten());
+
+        let config = Config::from_json_str(
+            r#"{
+                "debug": true,
+                "missing": null,
+                "database": {"host": "x", "port": 5432},
+                "servers": [{"host": "a"}, {"host": "b", "ports": [80]}]
+            }"#,
+        );
+        assert_eq!(expected, config.flatten());
+    }
 
-use serde::Deserialize;
+    #[test]
+    fn diff() {
+        let old = Config::from_toml_str(
+            r#"
+            name = "app"
+            removed = 1
+            ports = [80, 443]
 
-/* ------------------------------ */
-/* Generic initialization methods */
-/* ------------------------------ */
-#[derive(Clone)]
-pub enum Config {
-    Toml(toml::Table),
-    Json(serde_json::Value),
-}
+            [database]
+            host = "localhost"
+            port = 5432
+            "#,
+        );
+        let new = Config::from_toml_str(
+            r#"
+            name = "app"
+            ports = [80, 8443]
+            added = true
 
-impl From<toml::Table> for Config {
-    fn from(value: toml::Table) -> Self {
-        Self::Toml(value)
+            [database]
+            host = "db.example.com"
+            port = 5432
+            user = "admin"
+            "#,
+        );
+
+        // Sorted by key, the order of the entries depends on `preserve_order`
+        let mut diffs = old.diff(&new);
+        diffs.sort_by(|a, b| a.key().cmp(b.key()));
+
+        let value = |value: &str| toml::Value::from(value);
+        assert_eq!(
+            vec![
+                ConfigDiff::Added {
+                    key: String::from("added"),
+                    value: ConfigValue::Toml(&toml::Value::from(true)),
+                },
+                ConfigDiff::Changed {
+                    key: String::from("database.host"),
+                    old: ConfigValue::Toml(&value("localhost")),
+                    new: ConfigValue::Toml(&value("db.example.com")),
+                },
+                ConfigDiff::Added {
+                    key: String::from("database.user"),
+                    value: ConfigValue::Toml(&value("admin")),
+                },
+                ConfigDiff::Changed {
+                    key: String::from("ports"),
+                    old: ConfigValue::Toml(&toml::Value::from(vec![80, 443])),
+                    new: ConfigValue::Toml(&toml::Value::from(vec![80, 8443])),
+                },
+                ConfigDiff::Removed {
+                    key: String::from("removed"),
+                    value: ConfigValue::Toml(&toml::Value::from(1)),
+                },
+            ],
+            diffs
+        );
+
+        assert!(old.diff(&old).is_empty());
+
+        // Json configs are compared the same way
+        let old = Config::from_json_str(r#"{"a": {"b": 1, "c": 2}}"#);
+        let new = Config::from_json_str(r#"{"a": {"b": 1, "c": 3}}"#);
+        let diffs = old.diff(&new);
+        let keys: Vec<&str> = diffs.iter().map(ConfigDiff::key).collect();
+        assert_eq!(vec!["a.c"], keys);
     }
-}
 
-impl From<serde_json::Value> for Config {
-    fn from(value: serde_json::Value) -> Self {
-        Self::Json(value)
+    #[test]
+    fn to_format() {
+        let toml_config = Config::from_toml_str(
+            r#"
+            name = "app"
+            ratio = 0.5
+            ports = [80, 443]
+
+            [database]
+            port = 5432
+            "#,
+        );
+        let json_config = Config::from_json_str(
+            r#"{
+                "name": "app",
+                "ratio": 0.5,
+                "ports": [80, 443],
+                "database": {"port": 5432}
+            }"#,
+        );
+
+        assert_eq!(Format::Toml, toml_config.format());
+        assert_eq!(Format::Json, json_config.format());
+
+        // Round trip between the formats
+        let converted = toml_config.to_format(Format::Json).unwrap();
+        assert_eq!(Format::Json, converted.format());
+        assert!(converted.diff(&json_config).is_empty());
+        let converted = json_config.to_format(Format::Toml).unwrap();
+        assert!(converted.diff(&toml_config).is_empty());
+
+        // Lossy conversions
+        let config = Config::from_toml_str("[run]\nstart = 2024-01-01\n");
+        match config.to_format(Format::Json) {
+            Err(Error::Conversion { key, .. }) => assert_eq!("run.start", key),
+            _ => panic!("Datetime was converted to json"),
+        }
+
+        for json in [
+            r#"{"a": [1, null]}"#,
+            r#"{"a": 18446744073709551615}"#,
+            r#"[1, 2]"#,
+        ] {
+            let config = Config::from_json_str(json);
+            assert!(matches!(
+                config.to_format(Format::Toml),
+                Err(Error::Conversion { .. })
+            ));
+        }
     }
-}
 
-impl Config {
-    // Initialize Config from strings
-    pub fn from_toml_str(config_str: &str) -> Self {
-        Self::from(config_str.parse::<toml::Table>().unwrap_or_else(|_| {
-            panic!("Unable to parse toml config string {config_str}")
-        }))
+    #[test]
+    fn deprecated_keys() {
+        let mapping = [
+            ("output_dir", "output.path"),
+            ("run.n_steps", "run.steps"),
+            ("unused", "used"),
+        ];
+
+        for mut config in [
+            Config::from_toml_str(
+                "output_dir = \"out\"\n[run]\nn_steps = 10\nsteps = 20\n",
+            ),
+            Config::from_json_str(
+                r#"{"output_dir": "out", "run": {"n_steps": 10, "steps": 20}}"#,
+            ),
+        ] {
+            assert_eq!(
+                vec!["output_dir", "run.n_steps"],
+                config.warn_deprecated(&mapping)
+            );
+
+            // Values already set under the new key are kept
+            assert_eq!(vec!["output_dir"], config.migrate_deprecated(&mapping));
+            let expected = Config::from_toml_str(
+                "output_dir = \"out\"\n[output]\npath = \"out\"\n\
+                [run]\nn_steps = 10\nsteps = 20\n",
+            )
+            .to_format(config.format())
+            .unwrap();
+            assert!(config.diff(&expected).is_empty());
+        }
     }
 
-    pub fn from_json_str(config_str: &str) -> Self {
-        Self::from(
-            serde_json::from_str::<serde_json::Value>(config_str)
-                .unwrap_or_else(|_| {
-                    panic!("Unable to parse json string {config_str}")
-                }),
-        )
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    enum Interpolation {
+        Linear { slope: f64 },
+        Spline { knots: u32 },
     }
-}
 
-pub fn load_config(filename: &Path) -> Config {
-    match filename.extension() {
-        Some(extension) => {
-            let extension_str = extension.to_str().unwrap();
-            match extension_str {
-                "toml" => load_toml(filename),
-                "json" => load_json(filename),
-                _ => panic!(
-                    "Config files with .{extension_str} extension \
-                                  are not supported."
-                ),
-            }
+    #[test]
+    fn tagged_enum() {
+        let config = Config::from_toml_str(
+            r#"
+            [linear]
+            type = "linear"
+            slope = 0.5
+
+            [spline]
+            type = "spline"
+            knots = 8
+
+            [unknown]
+            type = "cubic"
+
+            [incomplete]
+            type = "linear"
+
+            [untagged]
+            slope = 0.5
+            "#,
+        );
+
+        for config in [config.clone(), config.to_format(Format::Json).unwrap()]
+        {
+            let parse = |table: &str| {
+                from_config_tagged::<Interpolation>(&config, table, "type")
+            };
+            let reason = |table: &str| parse(table).unwrap_err().to_string();
+
+            assert_eq!(
+                Interpolation::Linear { slope: 0.5 },
+                parse("linear").unwrap()
+            );
+            assert_eq!(
+                Interpolation::Spline { knots: 8 },
+                parse("spline").unwrap()
+            );
+
+            assert!(reason("unknown").contains(r#"type = "cubic""#));
+            assert!(reason("unknown").contains("expected `linear` or `spline`"));
+            assert!(reason("incomplete").contains(r#"type = "linear""#));
+            assert!(reason("incomplete").contains("missing field `slope`"));
+            assert!(reason("untagged").contains(r#"no "type" tag"#));
+            assert!(!reason("unknown").contains('\n'));
         }
-        None => panic!(
-            "No extension found for config file {}.",
-            filename.to_str().unwrap()
-        ),
     }
-}
 
-// Method to deserialize a config into the target structure
-pub trait FromConfig: for<'a> Deserialize<'a> {
-    fn from_config(config: &Config, table_name: &str) -> Self {
-        match config {
-            Config::Toml(config) => {
-                match config[table_name].clone().try_into() {
-                    Ok(value) => value,
-                    Err(e) => panic!(
-                        "Failed to initialize the structure for sub-table {table_name}: {e}"
-                    ),
-                }
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct StrictStruct {
+        x: u32,
+        y: u32,
+        z: u32,
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let config = Config::from_toml_str(
+            "[exact]\nx = 1\ny = 2\nz = 3\n[extra]\nx = 1\ny = 2\nz = 3\nw = 4\nv = 5\n",
+        );
+
+        // deny_unknown_fields is honored through FromConfig
+        assert!(StrictStruct::try_from_config(&config, "exact").is_ok());
+        let reason = StrictStruct::try_from_config(&config, "extra")
+            .unwrap_err()
+            .to_string();
+        assert!(reason.contains("unknown field `"));
+
+        // The helper lists every unknown key, even without the attribute
+        assert_eq!(
+            TestStruct { x: 1, y: 2, z: 3 },
+            from_config_strict::<TestStruct>(&config, "exact").unwrap()
+        );
+        let reason = from_config_strict::<TestStruct>(&config, "extra")
+            .unwrap_err()
+            .to_string();
+        assert!(reason.contains("`w`"));
+        assert!(reason.contains("`v`"));
+        assert!(reason.contains("expected `x`, `y`, `z`"));
+    }
+
+    #[test]
+    fn get_insensitive() {
+        let keys = ["output_dir", "Output_Dir", "outputDir", "output-dir"];
+
+        for key in keys {
+            let toml_config =
+                Config::from_toml_str(&format!("{key} = \"results\""));
+            let json_config =
+                Config::from_json_str(&format!(r#"{{"{key}": "results"}}"#));
+
+            for query in keys.iter().chain(&["OUTPUTDIR", "OutputDir"]) {
+                assert_eq!(
+                    Some(ConfigValue::Toml(&toml::Value::from("results"))),
+                    toml_config.get_insensitive(query),
+                    "{query} does not match {key}"
+                );
+                assert_eq!(
+                    Some(ConfigValue::Json(&serde_json::Value::from(
+                        "results"
+                    ))),
+                    json_config.get_insensitive(query),
+                    "{query} does not match {key}"
+                );
             }
-            Config::Json(config) => {
-                match serde_json::from_value(config[table_name].clone()) {
-                    Ok(value) => value,
-                    Err(e) => panic!(
-                            "Failed to initialize the structure for sub-table {table_name}: {e}"
-                        ),
+
+            assert_eq!(None, toml_config.get_insensitive("output_path"));
+            assert_eq!(None, json_config.get_insensitive("output_path"));
+        }
+
+        // Exact matches take precedence
+        let config = Config::from_toml_str(
+            r#"
+            output_dir = "first"
+            outputDir = "second"
+            "#,
+        );
+        assert_eq!(
+            Some(ConfigValue::Toml(&toml::Value::from("second"))),
+            config.get_insensitive("outputDir")
+        );
+    }
+
+    #[test]
+    fn sequence_from_config() {
+        let configs = [
+            Config::from_toml_str(
+                r#"
+            [[data]]
+            x = 1
+            y = 2
+            z = 3
+
+            [[data]]
+            x = 4
+            y = 5
+            z = 6
+
+            [single]
+            x = 1
+            y = 2
+            z = 3
+            "#,
+            ),
+            Config::from_json_str(
+                r#"
+            {
+                "data": [
+                    {"x": 1, "y": 2, "z": 3},
+                    {"x": 4, "y": 5, "z": 6}
+                ],
+                "single": {"x": 1, "y": 2, "z": 3}
+            }"#,
+            ),
+        ];
+
+        for config in configs {
+            let test_structs: Vec<TestStruct> =
+                from_config_seq(&config, "data").unwrap();
+
+            assert_eq!(
+                vec![
+                    TestStruct { x: 1, y: 2, z: 3 },
+                    TestStruct { x: 4, y: 5, z: 6 }
+                ],
+                test_structs
+            );
+
+            assert!(matches!(
+                from_config_seq::<TestStruct>(&config, "single"),
+                Err(Error::Deserialize { .. })
+            ));
+            assert!(matches!(
+                from_config_seq::<TestStruct>(&config, "servers"),
+                Err(Error::MissingKeys { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn optional_table() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Settings {
+            x: u32,
+            #[serde(default = "default_y")]
+            y: u32,
+        }
+
+        fn default_y() -> u32 {
+            7
+        }
+
+        impl Default for Settings {
+            fn default() -> Self {
+                Self {
+                    x: 1,
+                    y: default_y(),
                 }
             }
         }
-    }
-}
 
-impl<T: for<'a> Deserialize<'a>> FromConfig for T {}
+        let configs = [
+            Config::from_toml_str("[settings]\nx = 5\n"),
+            Config::from_json_str(r#"{"settings": {"x": 5}}"#),
+        ];
 
-/* ---------------------------------------- */
-/* Method for loading data from .toml files */
-/* ---------------------------------------- */
+        for config in configs {
+            // Absent table
+            assert_eq!(
+                Settings { x: 1, y: 7 },
+                from_config_or_default(&config, "other")
+            );
 
-// Open a config.toml file and save the data as a toml::Value
-fn load_toml(filename: &Path) -> Config {
-    // Read the contents of the file
-    let contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        panic!("Problem opening the file: {}", filename.to_str().unwrap())
-    });
+            // Present table with a missing field
+            assert_eq!(
+                Settings { x: 5, y: 7 },
+                from_config_or_default(&config, "settings")
+            );
+        }
+    }
 
-    // Save the data to toml::Table
-    Config::Toml(contents.parse::<toml::Table>().unwrap_or_else(|_| {
-        panic!(
-            "{} should contain a table-type data.",
-            filename.to_str().unwrap()
+    #[test]
+    fn resolve_layers() {
+        let temp = TempProject::new("toml", OverwriteType::Overwrite).unwrap();
+        let path = &temp.path().join("config.toml");
+        std::fs::write(
+            path,
+            "[database]\nhost = \"db\"\nport = 5432\n[run]\nsteps = 10\n",
         )
-    }))
-}
+        .unwrap();
+        let env = [
+            ("IO_UTILS_RESOLVE_DATABASE__PORT", "6543"),
+            ("IO_UTILS_RESOLVE_RUN__LABEL", "from env"),
+            ("OTHER_RUN__LABEL", "ignored"),
+        ];
 
-/* ---------------------------------------- */
-/* Method for loading data from .json files */
-/* ---------------------------------------- */
+        let defaults = Config::from_json_str(
+            r#"{"database": {"host": "localhost", "user": "admin"}}"#,
+        );
+        let config = ConfigResolver::new()
+            .with_config(defaults)
+            .with_file(path)
+            .with_env_vars("IO_UTILS_RESOLVE", env)
+            .with_overrides([("run.steps", "20"), ("run.debug", "true")])
+            .resolve()
+            .unwrap();
 
-// Open a config.toml file and save the data as a toml::Value
-fn load_json(filename: &Path) -> Config {
-    // Read the contents of the file
-    let contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        panic!("Problem opening the file: {}", filename.to_str().unwrap())
-    });
+        // Later sources win, the format is the one of the defaults
+        assert_eq!(Format::Json, config.format());
+        let expected: BTreeMap<String, String> = [
+            ("database.host", "db"),
+            ("database.port", "6543"),
+            ("database.user", "admin"),
+            ("run.debug", "true"),
+            ("run.label", "from env"),
+            ("run.steps", "20"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(expected, config.flatten());
+        assert_eq!(
+            Some(&serde_json::Value::from(6543)),
+            match config.data() {
+                ConfigData::Json(json) => json.pointer("/database/port"),
+                _ => None,
+            }
+        );
 
-    // Save the data to toml::Table
-    Config::Json(serde_json::from_str(&contents).unwrap_or_else(|_| {
-        panic!(
-            "{} should contain a table-type data.",
-            filename.to_str().unwrap()
-        )
-    }))
-}
+        // A missing file fails the resolution
+        assert!(ConfigResolver::new()
+            .with_file(&temp.path().join("missing.toml"))
+            .resolve()
+            .is_err());
+        assert!(ConfigResolver::new().resolve().unwrap().keys().is_empty());
 
-#[cfg(test)]
-mod tests {
-    use super::{load_config, Config, Deserialize, FromConfig, Path};
+        // The environment of the process is read by default
+        let config = ConfigResolver::new()
+            .with_env("IO_UTILS_RESOLVE_UNSET")
+            .resolve()
+            .unwrap();
+        assert!(config.keys().is_empty());
+    }
 
-    use std::io::Write;
+    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    struct Common {
+        seed: u64,
+        output: String,
+    }
 
-    #[derive(Deserialize)]
-    struct TestStruct {
-        x: u32,
-        y: u32,
-        z: u32,
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Solver {
+        #[serde(flatten)]
+        common: Common,
+        tolerance: f64,
     }
 
-    fn touch(path: &Path) {
-        std::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(path)
-            .unwrap();
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Sampler {
+        #[serde(flatten)]
+        common: Common,
+        n_samples: u32,
     }
 
-    fn rm(path: &Path) {
-        std::fs::remove_file(path).unwrap();
+    #[test]
+    fn flattened_targets() {
+        let common = Common {
+            seed: 42,
+            output: String::from("out"),
+        };
+
+        // Fields of the nested structure inlined in the top level
+        for config in [
+            Config::from_toml_str(
+                "seed = 42\noutput = \"out\"\ntolerance = 1e-6\n",
+            ),
+            Config::from_json_str(
+                r#"{"seed": 42, "output": "out", "tolerance": 1e-6}"#,
+            ),
+        ] {
+            assert_eq!(
+                Solver {
+                    common: common.clone(),
+                    tolerance: 1e-6,
+                },
+                from_config_root(&config).unwrap()
+            );
+        }
+
+        // ... or in a table
+        let config = Config::from_toml_str(
+            "[solver]\nseed = 42\noutput = \"out\"\ntolerance = 1e-6\n",
+        );
+        assert_eq!(common, Solver::from_config(&config, "solver").common);
+        assert!(matches!(
+            from_config_root::<Solver>(&Config::from_toml_str("seed = 1\n")),
+            Err(Error::Deserialize { .. })
+        ));
+
+        // A shared section flattened into several targets
+        for config in [
+            Config::from_toml_str(
+                "[common]\nseed = 1\noutput = \"out\"\n\
+                [solver]\ntolerance = 1e-6\n\
+                [sampler]\nseed = 42\nn_samples = 100\n",
+            ),
+            Config::from_json_str(
+                r#"{
+                    "common": {"seed": 1, "output": "out"},
+                    "solver": {"tolerance": 1e-6},
+                    "sampler": {"seed": 42, "n_samples": 100}
+                }"#,
+            ),
+        ] {
+            let solver: Solver =
+                from_config_with_common(&config, "solver", "common").unwrap();
+            assert_eq!(1, solver.common.seed);
+            assert_eq!("out", solver.common.output);
+
+            // Keys of the table override the shared ones
+            let sampler: Sampler =
+                from_config_with_common(&config, "sampler", "common").unwrap();
+            assert_eq!(
+                Sampler {
+                    common: common.clone(),
+                    n_samples: 100,
+                },
+                sampler
+            );
+
+            assert!(matches!(
+                from_config_with_common::<Solver>(&config, "missing", "common"),
+                Err(Error::MissingKeys { .. })
+            ));
+        }
+
+        // Without the shared section the table must be complete
+        let config = Config::from_toml_str("[solver]\ntolerance = 1e-6\n");
+        assert!(matches!(
+            from_config_with_common::<Solver>(&config, "solver", "common"),
+            Err(Error::Deserialize { .. })
+        ));
     }
 
     #[test]
-    #[should_panic]
-    fn wrong_extension() {
-        let path = Path::new("file_with_wrong_extension.dat");
-        load_config(path);
+    fn config_table() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Database {
+            host: String,
+            port: u16,
+        }
+
+        impl ConfigTable for Database {
+            const TABLE_NAME: &'static str = "database";
+        }
+
+        let config = Config::from_toml_str(
+            "[database]\nhost = \"localhost\"\nport = 5432\n",
+        );
+        let expected = Database {
+            host: String::from("localhost"),
+            port: 5432,
+        };
+        assert_eq!(expected, Database::from_config_table(&config));
+
+        let config = Config::from_toml_str("[db]\nhost = \"localhost\"\n");
+        let error = Database::try_from_config_table(&config).unwrap_err();
+        assert!(error.to_string().contains("database"));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_config_table() {
+        #[super::config_table("project")]
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Project {
+            name: String,
+        }
+
+        assert_eq!("project", Project::TABLE_NAME);
+
+        let config = Config::from_toml_str("[project]\nname = \"run\"\n");
+        assert_eq!(
+            Project {
+                name: String::from("run")
+            },
+            Project::from_config_table(&config)
+        );
+    }
+
+    #[test]
+    fn interpolate() {
+        let mut toml_config = Config::from_toml_str(
+            r#"
+            base_dir = "/data"
+            log_dir = "${base_dir}/logs"
+            run = 3
+
+            [output]
+            path = "${log_dir}/run_${run}"
+            files = ["${output.path}/a.dat", "${base_dir}/b.dat"]
+            "#,
+        );
+        toml_config.interpolate().unwrap();
+
+        let mut json_config = Config::from_json_str(
+            r#"
+            {
+                "base_dir": "/data",
+                "log_dir": "${base_dir}/logs",
+                "run": 3,
+                "output": {
+                    "path": "${log_dir}/run_${run}",
+                    "files": ["${output.path}/a.dat", "${base_dir}/b.dat"]
+                }
+            }"#,
+        );
+        json_config.interpolate().unwrap();
+
+        #[derive(Deserialize)]
+        struct Output {
+            path: String,
+            files: Vec<String>,
+        }
+
+        for config in [toml_config, json_config] {
+            let output = Output::from_config(&config, "output");
+
+            assert_eq!("/data/logs/run_3", output.path);
+            assert_eq!(
+                vec!["/data/logs/run_3/a.dat", "/data/b.dat"],
+                output.files
+            );
+        }
+
+        // Cycles are detected
+        let mut config = Config::from_toml_str(
+            r#"
+            a = "${b}"
+            b = "x${c}"
+            c = "${a}"
+            "#,
+        );
+        match config.interpolate() {
+            Err(Error::Interpolation { reason, .. }) => {
+                assert_eq!("cyclic reference a -> b -> c -> a", reason)
+            }
+            _ => panic!("Cyclic reference was not detected"),
+        }
+
+        // Unknown references are reported
+        let mut config = Config::from_json_str(r#"{"a": "${missing.key}"}"#);
+        match config.interpolate() {
+            Err(Error::Interpolation { key, reason }) => {
+                assert_eq!("a", key);
+                assert_eq!("unknown reference ${missing.key}", reason);
+            }
+            _ => panic!("Unknown reference was not reported"),
+        }
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn preserve_order() {
+        let toml_contents = "b = 1\na = 2\n\n[d]\nz = 3\ny = 4\n\n[c]\nx = 5\n";
+        let json_contents = "{\n  \"b\": 1,\n  \"a\": 2\n}\n";
+
+        let configs = [
+            (
+                Config::from_toml_str(toml_contents),
+                "order.toml",
+                toml_contents,
+            ),
+            (
+                Config::from_json_str(json_contents),
+                "order.json",
+                json_contents,
+            ),
+        ];
+
+        for (config, file, contents) in configs {
+            let path = Path::new(file);
+            config.to_file(path).unwrap();
+
+            assert_eq!(contents, std::fs::read_to_string(path).unwrap());
+
+            rm(path);
+        }
     }
 
     mod toml_tests {
@@ -183,6 +2992,19 @@ mod tests {
             rm(path);
         }
 
+        #[test]
+        fn empty_file() {
+            let path = Path::new("test_empty.toml");
+            touch(path);
+
+            let config = try_load_config(path).unwrap();
+            assert_eq!(Format::Toml, config.format());
+            assert!(config.keys().is_empty());
+            assert!(Config::from_toml_str("").keys().is_empty());
+
+            rm(path);
+        }
+
         #[test]
         #[should_panic]
         fn file_not_found() {
@@ -192,7 +3014,7 @@ mod tests {
 
         #[test]
         fn sturct_from_config() {
-            let config = Config::Toml(
+            let config = Config::from(ConfigData::Toml(
                 toml::from_str(
                     r#"
             [data]
@@ -202,7 +3024,7 @@ mod tests {
             "#,
                 )
                 .unwrap(),
-            );
+            ));
             let test_struct = TestStruct::from_config(&config, "data");
 
             assert_eq!(test_struct.x, 1);
@@ -214,6 +3036,36 @@ mod tests {
     mod json_tests {
         use super::*;
 
+        #[test]
+        fn empty_file() {
+            let path = Path::new("test_empty.json");
+            touch(path);
+
+            // An empty file is an empty object, as in TOML
+            let config = try_load_config(path).unwrap();
+            assert_eq!(Format::Json, config.format());
+            assert!(config.keys().is_empty());
+            assert_eq!(
+                &serde_json::json!({}),
+                match config.data() {
+                    ConfigData::Json(json) => json,
+                    _ => unreachable!(),
+                }
+            );
+            assert!(Config::from_json_str(" \n").keys().is_empty());
+
+            // Missing tables are reported as usual
+            assert!(matches!(
+                TestStruct::try_from_config(&config, "data"),
+                Err(Error::MissingKeys { .. })
+            ));
+
+            // Other invalid contents still fail
+            assert!(Config::try_from_json_str("{").is_err());
+
+            rm(path);
+        }
+
         #[test]
         fn open_file() {
             // Create a new config file
@@ -243,7 +3095,7 @@ mod tests {
 
         #[test]
         fn sturct_from_config() {
-            let config = Config::Json(
+            let config = Config::from(ConfigData::Json(
                 serde_json::from_str(
                     r#"
                 {
@@ -255,7 +3107,56 @@ mod tests {
                 }"#,
                 )
                 .unwrap(),
-            );
+            ));
+            let test_struct = TestStruct::from_config(&config, "data");
+
+            assert_eq!(test_struct.x, 1);
+            assert_eq!(test_struct.y, 2);
+            assert_eq!(test_struct.z, 3);
+        }
+    }
+
+    #[cfg(feature = "json5")]
+    mod json5_tests {
+        use super::*;
+
+        const CONTENTS: &str = r#"
+        // Comments are allowed
+        {
+            data: {
+                x: 1,
+                y: 2,
+                z: 3, // and so are trailing commas
+            },
+        }"#;
+
+        #[test]
+        fn open_file() {
+            let path = Path::new("test.json5");
+            std::fs::write(path, CONTENTS).unwrap();
+
+            let config = load_config(path);
+            assert!(matches!(config.data(), ConfigData::Json(_)));
+
+            rm(path);
+        }
+
+        #[test]
+        fn empty_file() {
+            let path = Path::new("test_empty.json5");
+            std::fs::write(path, "\n").unwrap();
+
+            let config = try_load_config(path).unwrap();
+            assert_eq!(Format::Json, config.format());
+            assert!(config.keys().is_empty());
+            assert!(Config::from_json5_str("").keys().is_empty());
+
+            rm(path);
+        }
+
+        #[test]
+        fn sturct_from_config() {
+            let config = Config::from_json5_str(CONTENTS);
             let test_struct = TestStruct::from_config(&config, "data");
 
             assert_eq!(test_struct.x, 1);
@@ -0,0 +1,166 @@
+// Copyright Andrey Zelenskiy, 2025
+use std::{fmt, io, path::PathBuf};
+
+use crate::config_parse::Format;
+
+/// Errors returned by the fallible methods of the crate
+#[derive(Debug)]
+pub enum Error {
+    // Failure of a filesystem operation
+    Io(io::Error),
+    // Required top-level keys that are absent from a config
+    MissingKeys {
+        keys: Vec<String>,
+        source_path: Option<PathBuf>,
+    },
+    // Config file with an unsupported format or invalid contents
+    Parse {
+        path: PathBuf,
+        reason: String,
+    },
+    // Config string (or embedded bytes) with invalid contents
+    ParseStr {
+        format: Format,
+        reason: String,
+    },
+    // Failure to deserialize a config entry into the target structure
+    Deserialize {
+        table_name: String,
+        reason: String,
+        source_path: Option<PathBuf>,
+    },
+    // Config value that cannot be represented in the target format
+    Conversion {
+        key: String,
+        reason: String,
+    },
+    // Failure to resolve a ${key} reference in a config value
+    Interpolation {
+        key: String,
+        reason: String,
+    },
+    // Path that cannot be used for an output file
+    InvalidPath {
+        path: PathBuf,
+        reason: String,
+    },
+    // File name that cannot be used on all platforms
+    InvalidName {
+        name: String,
+        reason: String,
+    },
+    // Inconsistent settings of a file series
+    InvalidSeries {
+        name: String,
+        reason: String,
+    },
+    // Output file without write permissions (e.g. kept by
+    // OverwriteType::Ignore or not initialized)
+    NotWritable {
+        path: PathBuf,
+    },
+    // Config parameter with a value outside of its valid range
+    InvalidValue {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    // Not enough disk space for the expected size of the output files
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(reason) => write!(f, "{reason}"),
+            Self::MissingKeys { keys, source_path } => {
+                write!(
+                    f,
+                    "Config is missing the required section(s): {}",
+                    keys.iter()
+                        .map(|key| format!("[{key}]"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )?;
+                write_source_path(f, source_path)
+            }
+            Self::Parse { path, reason } => {
+                write!(f, "Failed to parse config file {path:?}: {reason}")
+            }
+            Self::ParseStr { format, reason } => {
+                write!(f, "Failed to parse {format} config: {reason}")
+            }
+            Self::Deserialize {
+                table_name,
+                reason,
+                source_path,
+            } => {
+                write!(f, "Failed to parse [{table_name}]")?;
+                write_source_path(f, source_path)?;
+                write!(f, ": {reason}")
+            }
+            Self::Conversion { key, reason } => {
+                write!(f, "Failed to convert config key {key}: {reason}")
+            }
+            Self::Interpolation { key, reason } => {
+                write!(f, "Failed to interpolate config key {key}: {reason}")
+            }
+            Self::InvalidPath { path, reason } => {
+                write!(f, "Invalid path {path:?}: {reason}")
+            }
+            Self::InvalidName { name, reason } => {
+                write!(f, "Invalid file name {name:?}: {reason}")
+            }
+            Self::InvalidSeries { name, reason } => {
+                write!(f, "Invalid file series {name:?}: {reason}")
+            }
+            Self::NotWritable { path } => {
+                write!(f, "File {path:?} does not have write permissions")
+            }
+            Self::InvalidValue {
+                field,
+                value,
+                reason,
+            } => write!(f, "Invalid value {value} of {field}: {reason}"),
+            Self::InsufficientSpace {
+                path,
+                required,
+                available,
+            } => write!(
+                f,
+                "Not enough disk space at {path:?}: {required} bytes \
+                expected, {available} bytes available"
+            ),
+        }
+    }
+}
+
+// Append the file a config was loaded from to the message
+fn write_source_path(
+    f: &mut fmt::Formatter<'_>,
+    source_path: &Option<PathBuf>,
+) -> fmt::Result {
+    match source_path {
+        Some(path) => write!(f, " in {}", path.display()),
+        None => Ok(()),
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
@@ -2,1023 +2,7588 @@
 
 use std::{fmt, fs, io};
 
-use io::Write;
+use io::{Read, Write};
 
-use std::fs::{copy, create_dir_all, OpenOptions};
+use std::fs::OpenOptions;
 
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use std::sync::Arc;
+
+use std::thread;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::compression::{self, Compression};
+
+use crate::config_parse::{
+    deserialized_source_path, from_config_seq, try_load_config, Config,
+    FromConfig,
+};
+
+use crate::error::Error;
+
+use std::collections::HashMap;
+
+#[cfg(feature = "checksum")]
+use std::collections::BTreeMap;
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+#[cfg(feature = "locking")]
+use fs2::FileExt;
+
+#[cfg(feature = "checksum")]
+use sha2::{Digest, Sha256};
+
 /// Structure to setup a project directory
 #[derive(Deserialize)]
 pub struct ProjectManager {
     // Path to the project directory
     path: String,
-    // Output file extension
-    extension: String,
+    // Output file extension (files keep their own extensions if not set)
+    #[serde(default)]
+    extension: Option<String>,
     // Type of behaviour if project files already exist
     overwrite_type: OverwriteType,
+    // Replace per-file extensions with the project extension
+    #[serde(default)]
+    force_extension: bool,
+    // Resolve a relative path against the directory of the config file
+    // instead of the current directory
+    #[serde(default)]
+    resolve_relative_to_config: bool,
+    // Write the output files to a staging directory next to the project
+    // directory, moved to the project path by publish
+    #[serde(default)]
+    staged: bool,
+    // Retries of the filesystem operations failing with transient errors
+    #[serde(default)]
+    retry: RetryPolicy,
+    // Custom strategy for archiving files (copies them if not set)
+    #[serde(skip)]
+    archive_fn: Option<ArchiveFn>,
+    // Source of the current time (the system time if not set)
+    #[serde(skip)]
+    clock: Option<Box<dyn Clock + Send + Sync>>,
+    // Filesystem of the initialization (std::fs if not set)
+    #[serde(skip)]
+    file_system: Option<Arc<dyn FileSystem>>,
+    // Hook called with every file created by the initialization
+    #[serde(skip)]
+    post_init: Option<PostInitFn>,
 }
 
-// Instructions for dealing with files that already exist
-#[derive(Deserialize)]
-pub enum OverwriteType {
-    // Interrupts the program if duplicates are located
-    Panic,
-    // Copies duplicates to an archive folder
-    Archive,
-    // Overwrites all files
-    Overwrite,
-    // Ignore existing file during the writing
-    Ignore,
+/// Destination of the data written through a FileManager
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Target {
+    // Output file at the path built from the components (default)
+    File,
+    // Standard output stream (e.g. for the `--output -` convention)
+    Stdout,
+    // Standard error stream
+    Stderr,
 }
 
-impl ProjectManager {
-    /// Returns the path to the project directory
-    pub fn path(&self) -> &str {
-        &self.path
-    }
+/// Line terminator of the lines written through a FileManager
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum LineEnding {
+    // "\n" (Unix)
+    Lf,
+    // "\r\n" (Windows)
+    Crlf,
+}
 
-    /// Modifies path of the project
-    pub fn set_path(&mut self, path: String) {
-        self.path = path;
+impl LineEnding {
+    /// Returns the line ending of the platform
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
     }
 
-    /// Initializes output files
-    pub fn initialize_output_files(
-        &self,
-        files: Vec<&mut FileManager>,
-    ) -> Result<(), String> {
-        files
-            .into_iter()
-            .map(|file| {
-                file.set_project_path(&self.path)
-                    .set_extension(&self.extension)
-                    .set_path()
-            })
-            .try_for_each(|file| self.try_initialize_output(file))
+    /// Returns the terminator itself
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
     }
+}
 
-    /// Attempts to initialize output files depending on the overwrite
-    /// conditions
-    fn try_initialize_output(
-        &self,
-        file: &mut FileManager,
-    ) -> Result<(), String> {
-        match &self.overwrite_type {
-            OverwriteType::Panic => {
-                if file.path().exists() {
-                    Err(String::from(
-                        "Permission denied to overwrite existing output files.",
-                    ))
-                } else {
-                    file.initialize_output();
-                    Ok(())
-                }
-            }
-            OverwriteType::Archive => {
-                // Create an archive directory
-                if file.path().exists() {
-                    let archive_path = Path::new(&self.path).join("archive");
-
-                    if !archive_path.exists() {
-                        if let Err(reason) = create_dir_all(&archive_path) {
-                            panic!(
-                                "Unable to create archive directory {:?}: {:?}",
-                                archive_path, reason
-                            );
-                        }
-                    }
-                    self.move_to_archive(file.path(), &archive_path);
-                }
+/// Alignment of the values in a fixed-width column
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Alignment {
+    // Padded on the right
+    Left,
+    // Padded on the left (the usual layout of numbers)
+    #[default]
+    Right,
+}
 
-                file.initialize_output();
-                Ok(())
-            }
-            OverwriteType::Overwrite => {
-                file.initialize_output();
-                Ok(())
-            }
-            OverwriteType::Ignore => {
-                if file.path().exists() {
-                    file.change_write_permission(false);
-                } else {
-                    file.initialize_output();
-                }
-                Ok(())
-            }
+/// Layout of fixed-width columns written by FileManager::write_columns (e.g.
+/// for tools reading Fortran formatted records), the columns are written
+/// next to each other so the widths should include the spacing
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ColumnSpec {
+    // Width and alignment of each column
+    columns: Vec<Column>,
+    // Option to refuse values wider than their column instead of
+    // truncating them
+    #[serde(default)]
+    strict: bool,
+}
+
+// Fixed-width column of a ColumnSpec
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+struct Column {
+    // Width in characters
+    width: usize,
+    // Side of the value within the column
+    #[serde(default)]
+    alignment: Alignment,
+}
+
+impl ColumnSpec {
+    /// Creates a layout of right-aligned columns with the widths (in
+    /// characters), wider values are truncated
+    pub fn new(widths: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            columns: widths
+                .into_iter()
+                .map(|width| Column {
+                    width,
+                    alignment: Alignment::Right,
+                })
+                .collect(),
+            strict: false,
         }
     }
 
-    /// Moves a file to archive
-    fn move_to_archive(&self, file_path: &Path, archive_path: &Path) {
-        let filename = match file_path.file_name() {
-            None => panic!(
-                "Cannot move file to archive, no file name in path {:?}",
-                file_path
-            ),
-            Some(name) => name,
-        };
+    /// Sets the alignment of the column (ignored for a missing column)
+    pub fn set_alignment(
+        &mut self,
+        column: usize,
+        alignment: Alignment,
+    ) -> &mut Self {
+        if let Some(column) = self.columns.get_mut(column) {
+            column.alignment = alignment;
+        }
+        self
+    }
 
-        let relative_path = match file_path.parent() {
-            None => panic!(
-                "Cannot move file to archive, no parent directory in path {:?}",
-                file_path
-            ),
-            Some(full_path) => match full_path.file_name() {
-                None => panic!(
-                "Cannot move file to archive, no output directory in path {:?}",
-                file_path
-            ),
-                Some(path) => path,
-            },
-        };
+    /// Sets whether values wider than their column are refused instead of
+    /// truncated
+    pub fn set_strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
 
-        if !archive_path.join(relative_path).exists() {
-            if let Err(reason) =
-                create_dir_all(archive_path.join(relative_path))
-            {
-                panic!(
-                    "Cannot create {:?} directory: {:?}",
-                    archive_path.join(relative_path),
-                    reason
-                );
-            }
+    /// Returns the widths of the columns
+    pub fn widths(&self) -> Vec<usize> {
+        self.columns.iter().map(|column| column.width).collect()
+    }
+
+    /// Formats the values as a line of fixed-width columns, fails if the
+    /// number of values differs from the number of columns or if a value
+    /// is too wide for a strict layout
+    pub fn format(&self, values: &[&str]) -> Result<String, String> {
+        if values.len() != self.columns.len() {
+            return Err(format!(
+                "expected {} values, got {}",
+                self.columns.len(),
+                values.len()
+            ));
         }
 
-        if let Err(reason) =
-            copy(file_path, archive_path.join(relative_path).join(filename))
+        let mut line = String::new();
+        for (i, (value, column)) in values.iter().zip(&self.columns).enumerate()
         {
-            panic!(
-                "Cannot move file {:?} to {:?}: {:?}",
-                file_path,
-                archive_path.join(relative_path).join(filename),
-                reason
-            );
+            if self.strict && value.chars().count() > column.width {
+                return Err(format!(
+                    "value {value:?} exceeds the width {} of column #{i}",
+                    column.width
+                ));
+            }
+            line.push_str(&column.pad(value));
         }
+        Ok(line)
     }
-}
 
-impl fmt::Display for ProjectManager {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut summary = format!(
-            "Project path is {}\nOutput files have extension .{}\n\
-            Existing output files ",
-            self.path, self.extension
-        );
+    /// Formats the whitespace-separated names of a header line with the
+    /// widths (names without a column are kept as they are). The first
+    /// column is narrowed by the width of the prefix of the line (e.g. the
+    /// comment character) to keep the names above the values
+    fn format_header(&self, header: &str, prefix_width: usize) -> String {
+        let mut names = header.split_whitespace();
+        let mut line: String = self
+            .columns
+            .iter()
+            .enumerate()
+            .zip(names.by_ref())
+            .map(|((i, column), name)| match i {
+                0 => Column {
+                    width: column.width.saturating_sub(prefix_width),
+                    ..*column
+                }
+                .pad(name),
+                _ => column.pad(name),
+            })
+            .collect();
 
-        match &self.overwrite_type {
-            OverwriteType::Panic => summary.push_str(
-                "will interrupt the program (overwrite_typ = Panic).\n",
-            ),
-            OverwriteType::Archive => summary
-                .push_str("will be archived (overwrite_type = Archive).\n"),
-            OverwriteType::Overwrite => summary.push_str(
-                "will be overwritten (overwrite_type = Overwrite).\n",
-            ),
-            OverwriteType::Ignore => summary.push_str(
-                "will not be collected again during this run \
-                (overwirte_type = Ignore).\n",
-            ),
+        for name in names {
+            line.push(' ');
+            line.push_str(name);
         }
-
-        write!(f, "{summary}")
+        line
     }
 }
 
-/// Type for output file manipulation
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
-pub struct FileManager {
-    // Column descriptions in the output file
-    header: Option<String>,
-    // Project path
-    project_path: Option<String>,
-    // Output path (relative to the project path)
-    output_path: Option<String>,
-    // File name
-    name: Option<String>,
-    // File extension
-    extension: Option<String>,
-    // Option for a series of data files with related name/structure,
-    // stores number of files and current file index
-    series: Option<(u32, usize)>,
-    // Absolute path of the output file
-    #[serde(skip)]
-    path: Option<PathBuf>,
-    // Permission for writing to the file
-    #[serde(skip)]
-    writable: bool,
-}
-
-impl FileManager {
-    // Builder methods
+impl Column {
+    /// Pads (or truncates) the value to the width of the column
+    fn pad(&self, value: &str) -> String {
+        let value: String = value.chars().take(self.width).collect();
+        let width = self.width;
 
-    /// Return the initialization state
-    pub fn initialized(&self) -> bool {
-        self.path.is_some()
+        match self.alignment {
+            Alignment::Left => format!("{value:<width$}"),
+            Alignment::Right => format!("{value:>width$}"),
+        }
     }
+}
 
-    // Setters
-    // Note: the setters only work when self.initialized() = false, and
-    // the specified field has not been set yet.For all other cases,
-    // one must use Modifiers
+/// Series of data files with related name/structure, the files are named
+/// with the number `start + index` (name_0.dat, name_1.dat, ...)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "RawSeries", into = "RawSeries")]
+pub struct Series {
+    // Number of files in series
+    count: u32,
+    // Index of the current file
+    index: usize,
+    // Number of the first file in the file names
+    start: usize,
+}
 
-    /// Sets the header
-    pub fn set_header(&mut self, header: &str) -> &mut Self {
-        if !self.initialized() && self.header.is_none() {
-            self.header = Some(header.to_string());
+// A config may give only the number of files or the pair of the number of
+// files and the current index, a series is written as the pair unless it
+// has a start
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum RawSeries {
+    Count(u32),
+    Indexed(u32, usize),
+    Table {
+        count: u32,
+        #[serde(default)]
+        index: usize,
+        #[serde(default)]
+        start: usize,
+    },
+}
+
+impl From<RawSeries> for Series {
+    fn from(raw: RawSeries) -> Self {
+        match raw {
+            RawSeries::Count(count) => Series::new(count),
+            RawSeries::Indexed(count, index) => Series {
+                index,
+                ..Series::new(count)
+            },
+            RawSeries::Table {
+                count,
+                index,
+                start,
+            } => Series {
+                count,
+                index,
+                start,
+            },
         }
-        self
     }
+}
 
-    /// Adds a path to the root project directory
-    pub fn set_project_path(&mut self, project_path: &str) -> &mut Self {
-        if !self.initialized() && self.project_path.is_none() {
-            self.project_path = Some(project_path.to_string());
+impl From<Series> for RawSeries {
+    fn from(series: Series) -> Self {
+        match series.start {
+            0 => RawSeries::Indexed(series.count, series.index),
+            start => RawSeries::Table {
+                count: series.count,
+                index: series.index,
+                start,
+            },
         }
-        self
     }
+}
 
-    /// Sets the output directory path (relative to the project directory)
-    pub fn set_output_path(&mut self, output_path: &str) -> &mut Self {
-        if !self.initialized() && self.output_path.is_none() {
-            self.output_path = Some(output_path.to_string());
+impl Series {
+    /// Returns a series of the number of files, starting at the first file
+    pub fn new(count: u32) -> Self {
+        Series {
+            count,
+            index: 0,
+            start: 0,
         }
-        self
     }
 
-    /// Sets the name of the output file
-    pub fn set_file_name(&mut self, name: &str) -> &mut Self {
-        if !self.initialized() && self.name.is_none() {
-            self.name = Some(name.to_string());
-        }
-        self
+    /// Returns the number of files in series
+    pub fn count(&self) -> u32 {
+        self.count
     }
 
-    /// Sets the extension of the ouput file
-    pub fn set_extension(&mut self, extension: &str) -> &mut Self {
-        if !self.initialized() && self.extension.is_none() {
-            self.extension = Some(extension.to_string());
-        }
-        self
+    /// Returns the index of the current file
+    pub fn index(&self) -> usize {
+        self.index
     }
 
-    /// Sets the number of files in series
-    pub fn set_series(&mut self, n_files: u32) -> &mut Self {
-        if !self.initialized() && self.series.is_none() {
-            self.series = Some((n_files, 0));
-        }
-        self
+    /// Returns the number of the first file in the file names
+    pub fn start(&self) -> usize {
+        self.start
     }
 
-    /// Attempts to set the path to the file
-    fn set_path(&mut self) -> &mut Self {
-        self.path = self.calculate_path();
-        self
+    /// Returns the number of the file with the index in the file names
+    pub fn number(&self, index: usize) -> usize {
+        self.start + index
     }
+}
 
-    /// Attempts to calculate the path to the file
-    fn calculate_path(&mut self) -> Option<PathBuf> {
-        // To initialize the path, self.project_path, self.output_path,
-        // self.name, and self.extension must be set
-        match (
-            &self.project_path,
-            &self.output_path,
-            &self.name,
-            &self.extension,
-        ) {
-            (
-                Some(project_path),
-                Some(output_path),
-                Some(name),
-                Some(extension),
-            ) => {
-                let mut path = PathBuf::new();
+/// Progress of the initialization of output files
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    // Initialization started, total number of files (including series
+    // members) to create
+    Started {
+        total: usize,
+    },
+    // File was created, files that are ignored (OverwriteType::Ignore) are
+    // not reported
+    FileCreated {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+    },
+    // All files were initialized
+    Finished,
+}
 
-                path.push(project_path);
+/// Policy for retrying filesystem operations that fail with transient
+/// errors (e.g. on network filesystems), no retries by default
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RetryPolicy {
+    // Number of retries after the first failed attempt
+    #[serde(default)]
+    retries: u32,
+    // Delay before the first retry in milliseconds, doubled for every
+    // following retry (up to MAX_BACKOFF)
+    #[serde(default)]
+    backoff_ms: u64,
+}
 
-                path.push(output_path);
+impl RetryPolicy {
+    /// Longest delay between two attempts
+    pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
 
-                // If dealing with file series, add the index of the file
-                let file_name = match &self.series {
-                    Some((_, index)) => &format!("{name}_{index}"),
-                    None => name,
-                };
+    /// Creates a policy with the number of retries and the initial backoff
+    pub fn new(retries: u32, backoff: Duration) -> Self {
+        Self {
+            retries,
+            backoff_ms: u64::try_from(backoff.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
 
-                path.push(file_name);
+    /// Returns the delay before the retry (counted from 0), doubling the
+    /// initial backoff for every retry up to MAX_BACKOFF
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u64.checked_pow(retry).unwrap_or(u64::MAX);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor))
+            .min(Self::MAX_BACKOFF)
+    }
 
-                path.set_extension(extension);
+    /// Checks whether the error may disappear on its own
+    pub fn is_transient(error: &io::Error) -> bool {
+        matches!(
+            error.kind(),
+            io::ErrorKind::Interrupted
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::ResourceBusy
+        )
+    }
 
-                // Attempt to canonicalize the path
-                match path.canonicalize() {
-                    Ok(absolute_path) => Some(absolute_path),
-                    Err(_) => Some(path),
+    /// Runs the operation, retrying it while it fails with transient errors
+    pub fn run<T>(
+        &self,
+        mut operation: impl FnMut() -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut retries = 0;
+
+        loop {
+            match operation() {
+                Err(reason)
+                    if retries < self.retries
+                        && Self::is_transient(&reason) =>
+                {
+                    thread::sleep(self.backoff(retries));
+                    retries += 1;
                 }
+                result => return result,
             }
-            _ => None,
         }
     }
+}
 
-    /// Call to build the path
-    pub fn build(&mut self) -> Self {
-        self.set_path().clone()
+/// Function post-processing a file after its initialization (e.g. setting
+/// extended attributes or registering it in an index)
+pub type PostInitFn = Box<dyn Fn(&FileManager) -> io::Result<()> + Send + Sync>;
+
+/// Source of the current time for the time-dependent features, a fixed
+/// clock makes them deterministic in tests
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock reading the system time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
     }
+}
 
-    // Modifiers
-    // Note: the setters only work when self.initialized() = true.
-    // Otherwise, one must use Setters
+impl<F: Fn() -> SystemTime> Clock for F {
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
 
-    /// Changeis the path to the root project directory
-    pub fn change_project_path(&mut self, project_path: &str) {
-        if self.initialized() {
-            self.project_path = Some(project_path.to_string());
-            self.set_path();
-        }
+/// Filesystem operations of the output files, every method defaults to
+/// std::fs. An implementation overriding some of them makes the error paths
+/// (permission denied, disk full, ...) testable without provoking the errors
+/// on a real filesystem. The operations creating, modifying, moving and
+/// probing the outputs go through it; reading the data back (open_reader,
+/// checksums, Manifest::load), resolving canonical paths and the cleanup on
+/// drop use std::fs directly
+pub trait FileSystem: Send + Sync {
+    /// Creates the directory and its missing parents with the builder
+    /// options (e.g. the permissions), the builder is recursive
+    fn create_dir_all(
+        &self,
+        path: &Path,
+        builder: &fs::DirBuilder,
+    ) -> io::Result<()> {
+        builder.create(path)
     }
 
-    /// Changes the output directory path (relative to the project directory)
-    pub fn change_output_path(&mut self, output_path: &str) {
-        if self.initialized() {
-            self.output_path = Some(output_path.to_string());
-            self.set_path();
-        }
+    /// Opens the file with the options
+    fn open(&self, path: &Path, options: &OpenOptions) -> io::Result<fs::File> {
+        options.open(path)
     }
 
-    /// Changes the name of the output file
-    pub fn change_file_name(&mut self, name: &str) {
-        if self.initialized() {
-            self.name = Some(name.to_string());
-            self.set_path();
-        }
+    /// Copies the file, returns the number of copied bytes
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        fs::copy(from, to)
     }
 
-    /// Change the extension of the ouput file - use cautiously!
-    pub fn change_extension(&mut self, extension: &str) {
-        if self.initialized() {
-            self.extension = Some(extension.to_string());
-            self.set_path();
-        }
+    /// Renames (moves) the file
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
     }
 
-    /// Changes the file index if dealing with file series
-    pub fn change_file_index(&mut self, index: usize) {
-        if self.initialized() {
-            if let Some((n_files, _)) = self.series {
-                self.series = Some((n_files, index));
-                self.set_path();
-            }
-        }
+    /// Returns the metadata of the file or directory
+    fn metadata(&self, path: &Path) -> io::Result<fs::Metadata> {
+        fs::metadata(path)
     }
 
-    /// Changes write permissions
-    pub fn change_write_permission(&mut self, writable: bool) {
-        self.writable = writable;
+    /// Checks whether the file or directory exists
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
     }
 
-    /// Returns the path to the output file
-    pub fn path(&self) -> &PathBuf {
-        match &self.path {
-            Some(path) => path,
-            None => panic!(
-                "Attempting to access the path of uninitialized FileManager\n\
-                Curently,\nproject_path: {:?},\noutput_path: {:?},\
-                \nname: {:?},\nextension: {:?},\n",
-                self.project_path, self.output_path, self.name, self.extension
-            ),
+    /// Removes the directory with all of its contents
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    /// Returns the entries of the directory
+    fn read_dir(&self, path: &Path) -> io::Result<fs::ReadDir> {
+        fs::read_dir(path)
+    }
+
+    /// Removes the empty directory
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    /// Sets the permissions of the file or directory
+    fn set_permissions(
+        &self,
+        path: &Path,
+        permissions: fs::Permissions,
+    ) -> io::Result<()> {
+        fs::set_permissions(path, permissions)
+    }
+}
+
+/// Filesystem forwarding every operation to std::fs
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {}
+
+// Filesystem of a FileManager, shared with the project that initialized it
+#[derive(Clone)]
+struct SharedFileSystem(Arc<dyn FileSystem>);
+
+impl fmt::Debug for SharedFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedFileSystem").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for SharedFileSystem {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Function archiving a file (first argument) to the destination path
+/// (second argument)
+pub type ArchiveFn = Box<dyn Fn(&Path, &Path) -> io::Result<()> + Send + Sync>;
+
+// Instructions for dealing with files that already exist, a config may give
+// the name of the variant in any case (e.g. "archive" or "skip_if_unchanged")
+// or the integer code of the older tooling (see OverwriteType::CODES)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "RawOverwriteType")]
+pub enum OverwriteType {
+    // Interrupts the program if duplicates are located
+    Panic,
+    // Copies duplicates to an archive folder
+    Archive,
+    // Overwrites all files
+    Overwrite,
+    // Ignore existing file during the writing
+    Ignore,
+    // Keeps existing files whose contents are identical to the newly
+    // initialized ones (and keeps writing to them), overwrites the others.
+    // Only the initialization (byte order mark, preamble and header) is
+    // compared, so a file that already holds data is always overwritten
+    SkipIfUnchanged,
+    // Only resolve the paths of the files without creating anything (the
+    // files stay read-only)
+    None,
+}
+
+impl OverwriteType {
+    /// Integer codes of the overwrite types (the position in the array), the
+    /// codes 0-3 (Panic, Archive, Overwrite, Ignore) are the ones of the older
+    /// tooling, 4 (None) and 5 (SkipIfUnchanged) are new
+    pub const CODES: [OverwriteType; 6] = [
+        OverwriteType::Panic,
+        OverwriteType::Archive,
+        OverwriteType::Overwrite,
+        OverwriteType::Ignore,
+        OverwriteType::None,
+        OverwriteType::SkipIfUnchanged,
+    ];
+
+    /// Helper method looking up the overwrite type of an integer code
+    fn from_code(code: i64) -> Result<Self, Error> {
+        match usize::try_from(code)
+            .ok()
+            .and_then(|code| OverwriteType::CODES.get(code))
+        {
+            Some(overwrite_type) => Ok(*overwrite_type),
+            None => Err(Error::InvalidValue {
+                field: String::from("overwrite_type"),
+                value: code.to_string(),
+                reason: format!(
+                    "code must be in [0, {}]",
+                    OverwriteType::CODES.len() - 1
+                ),
+            }),
         }
     }
 
-    /// Returns the path to the output file as a string
-    pub fn path_string(&self) -> String {
-        match self.path().to_str() {
-            Some(path_str) => path_str.to_string(),
-            None => panic!(
-                "Could not convert file path {:?} to string",
-                self.path()
-            ),
+    /// Returns the name of the overwrite type
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverwriteType::Panic => "Panic",
+            OverwriteType::Archive => "Archive",
+            OverwriteType::Overwrite => "Overwrite",
+            OverwriteType::Ignore => "Ignore",
+            OverwriteType::SkipIfUnchanged => "SkipIfUnchanged",
+            OverwriteType::None => "None",
         }
     }
+}
 
-    // Initializer methods
+impl TryFrom<u8> for OverwriteType {
+    type Error = Error;
 
-    /// Creates the output file (or files if dealing with series)
-    pub fn initialize_output(&mut self) {
-        // Make sure that the path is initialized
-        if !self.initialized() {
-            self.set_path();
+    fn try_from(code: u8) -> Result<Self, Error> {
+        OverwriteType::from_code(code.into())
+    }
+}
+
+impl std::str::FromStr for OverwriteType {
+    type Err = Error;
+
+    // Case and separators (_ or -) are ignored, e.g. "skip-if-unchanged"
+    fn from_str(name: &str) -> Result<Self, Error> {
+        let normalized: String =
+            name.chars().filter(|c| !matches!(c, '_' | '-')).collect();
+
+        OverwriteType::CODES
+            .into_iter()
+            .find(|overwrite_type| {
+                overwrite_type.name().eq_ignore_ascii_case(&normalized)
+            })
+            .ok_or_else(|| Error::InvalidValue {
+                field: String::from("overwrite_type"),
+                value: format!("{name:?}"),
+                reason: format!(
+                    "must be one of {}",
+                    OverwriteType::CODES
+                        .iter()
+                        .map(OverwriteType::name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })
+    }
+}
+
+// A config may give the overwrite type by name or by its integer code
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawOverwriteType {
+    Code(i64),
+    Name(String),
+}
+
+impl TryFrom<RawOverwriteType> for OverwriteType {
+    type Error = Error;
+
+    fn try_from(raw: RawOverwriteType) -> Result<Self, Error> {
+        match raw {
+            RawOverwriteType::Code(code) => OverwriteType::from_code(code),
+            RawOverwriteType::Name(name) => name.parse(),
         }
+    }
+}
 
-        // Create output directory
-        match self.path().parent() {
-            None => panic!(
-                "No parent directory found for {:?} FileManager",
-                self.path()
-            ),
-            Some(path) => {
-                if !path.exists() {
-                    if let Err(reason) = create_dir_all(path) {
-                        panic!(
-                            "Cannot initialize output directory {:?}: {:?}",
-                            path, reason,
-                        );
-                    }
-                }
+impl ProjectManager {
+    /// Creates a new project manager, the extension may be None for projects
+    /// whose files keep their own extensions
+    pub fn new(
+        path: String,
+        extension: impl Into<Option<String>>,
+        overwrite_type: OverwriteType,
+    ) -> Self {
+        let extension: Option<String> = extension.into();
+
+        Self {
+            path,
+            extension: extension
+                .map(|extension| without_leading_dot(&extension).to_string()),
+            overwrite_type,
+            force_extension: false,
+            resolve_relative_to_config: false,
+            staged: false,
+            retry: RetryPolicy::default(),
+            archive_fn: None,
+            clock: None,
+            file_system: None,
+            post_init: None,
+        }
+    }
+
+    /// Extracts the project manager from a table of the config, a relative
+    /// path is resolved against the directory of the config file if
+    /// resolve_relative_to_config is set
+    ///
+    /// ```
+    /// use io_utils::config_parse::Config;
+    /// use io_utils::files::ProjectManager;
+    ///
+    /// let config = Config::from_toml_str(
+    ///     r#"
+    ///     [project]
+    ///     path = "output"
+    ///     extension = "dat"
+    ///     overwrite_type = "Archive"  # Panic, Archive, Overwrite or Ignore
+    ///     "#,
+    /// );
+    ///
+    /// let project = ProjectManager::from_config(&config, "project").unwrap();
+    /// assert_eq!("output", project.path());
+    /// ```
+    pub fn from_config(
+        config: &Config,
+        table_name: &str,
+    ) -> Result<Self, Error> {
+        let mut project =
+            <Self as FromConfig>::try_from_config(config, table_name)?;
+
+        if project.resolve_relative_to_config {
+            project.path =
+                relative_to_config(&project.path, config.source_path());
+        }
+        if let Some(extension) = &mut project.extension {
+            *extension = without_leading_dot(extension).to_string();
+        }
+        Ok(project)
+    }
+
+    /// Loads a config file and extracts the project manager from its
+    /// [project] table
+    pub fn from_config_file(path: &Path) -> Result<Self, Error> {
+        Self::from_config(&try_load_config(path)?, "project")
+    }
+
+    /// Sets whether the project extension replaces the extensions set on
+    /// individual files (by default, per-file extensions take precedence)
+    pub fn set_force_extension(&mut self, force_extension: bool) -> &mut Self {
+        self.force_extension = force_extension;
+        self
+    }
+
+    /// Sets whether the output files are initialized in the staging directory
+    /// (<path>.staging) instead of the project directory, the staging tree
+    /// then replaces the project directory at once with publish (or is
+    /// removed with abort) for all-or-nothing outputs
+    pub fn set_staged(&mut self, staged: bool) -> &mut Self {
+        self.staged = staged;
+        self
+    }
+
+    /// Returns the path to the staging directory of the project
+    pub fn staging_path(&self) -> PathBuf {
+        PathBuf::from(format!(
+            "{}.staging",
+            self.path.trim_end_matches(['/', '\\'])
+        ))
+    }
+
+    /// Replaces the project directory with the staging directory. A previous
+    /// project directory is kept until the staging tree is in place, the
+    /// tree is copied if it cannot be renamed (e.g. across filesystems). The
+    /// archive of the project directory is moved to the new tree, the other
+    /// contents of the previous directory are replaced. The paths of the
+    /// initialized FileManagers still point to the staging directory, so
+    /// publish once the files are written
+    pub fn publish(&self) -> io::Result<()> {
+        let file_system = self.file_system();
+        let is_dir = |path: &Path| {
+            file_system.metadata(path).is_ok_and(|meta| meta.is_dir())
+        };
+
+        let staging = self.staging_path();
+        if !is_dir(&staging) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No staging directory {staging:?} to publish"),
+            ));
+        }
+
+        let target = Path::new(self.path.trim_end_matches(['/', '\\']));
+
+        // Keep the archived outputs of the previous runs
+        let archive = target.join("archive");
+        let staged_archive = staging.join("archive");
+        let mut moved_archive = false;
+        if is_dir(&archive) {
+            if file_system.exists(&staged_archive) {
+                copy_dir_all(file_system, &archive, &staged_archive)?;
+            } else {
+                move_dir(file_system, &archive, &staged_archive)?;
+                moved_archive = true;
             }
         }
 
-        // Initialize file(s)
-        match &self.series {
-            None => Self::initialize_file(self.path().as_path(), &self.header),
-            Some((n_files, _)) => {
-                for i in 0..*n_files as usize {
-                    self.change_file_index(i);
-                    self.set_path();
+        let backup = PathBuf::from(format!("{}.previous", target.display()));
+        if file_system.exists(target) {
+            if file_system.exists(&backup) {
+                file_system.remove_dir_all(&backup)?;
+            }
+            move_dir(file_system, target, &backup)?;
+        }
 
-                    Self::initialize_file(self.path().as_path(), &self.header)
-                }
+        if let Err(reason) = move_dir(file_system, &staging, target) {
+            // Restore the previous project directory
+            if file_system.exists(&backup) {
+                let _ = file_system.remove_dir_all(target);
+                move_dir(file_system, &backup, target)?;
+            }
+            if moved_archive && file_system.exists(&staged_archive) {
+                move_dir(file_system, &staged_archive, &archive)?;
             }
+            return Err(reason);
         }
 
-        // Change the writing permissions
-        self.writable = true;
+        if file_system.exists(&backup) {
+            file_system.remove_dir_all(&backup)?;
+        }
+        Ok(())
+    }
 
-        // Attempt to canonicalize the path
-        match self.path().canonicalize() {
-            Ok(absolute_path) => self.path = Some(absolute_path),
-            Err(reason) => {
-                println!(
-                    "Could not canonicalize path {:?}, using relative \
-                        form: {:?}",
-                    self.path(),
-                    reason.kind()
-                );
+    /// Removes the staging directory (if any), keeping the project directory
+    pub fn abort(&self) -> io::Result<()> {
+        let staging = self.staging_path();
+        if self.file_system().exists(&staging) {
+            self.file_system().remove_dir_all(&staging)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the directory the output files are initialized in (the
+    /// staging directory if staged)
+    fn output_root(&self) -> String {
+        if self.staged {
+            self.staging_path().to_string_lossy().into_owned()
+        } else {
+            self.path.clone()
+        }
+    }
+
+    /// Sets the policy for retrying the creation of directories and files
+    /// failing with transient errors
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) -> &mut Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets a custom strategy for archiving files (e.g. moving or
+    /// compressing them) instead of copying them
+    pub fn set_archive_fn(
+        &mut self,
+        archive_fn: impl Fn(&Path, &Path) -> io::Result<()> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.archive_fn = Some(Box::new(archive_fn));
+        self
+    }
+
+    /// Sets a hook called with every file after its initialization (files
+    /// that are ignored or only resolved are skipped), an error of the hook
+    /// aborts the initialization
+    pub fn set_post_init(
+        &mut self,
+        post_init: impl Fn(&FileManager) -> io::Result<()> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.post_init = Some(Box::new(post_init));
+        self
+    }
+
+    /// Sets the source of the current time (the system time by default)
+    pub fn set_clock(
+        &mut self,
+        clock: impl Clock + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Returns the current time according to the clock of the project
+    pub fn now(&self) -> SystemTime {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => SystemClock.now(),
+        }
+    }
+
+    /// Sets the filesystem of the project (std::fs by default), e.g. to
+    /// simulate failures in tests. The files initialized by the project keep
+    /// using it (rotation, import, ...) unless they have their own
+    pub fn set_file_system(
+        &mut self,
+        file_system: impl FileSystem + 'static,
+    ) -> &mut Self {
+        self.file_system = Some(Arc::new(file_system));
+        self
+    }
+
+    /// Returns the filesystem used to initialize the output files
+    pub fn file_system(&self) -> &dyn FileSystem {
+        match &self.file_system {
+            Some(file_system) => file_system.as_ref(),
+            None => &RealFileSystem,
+        }
+    }
+
+    /// Returns the path to the project directory
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the extension of the output files (None if the files keep
+    /// their own extensions)
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Modifies path of the project
+    pub fn set_path(&mut self, path: String) {
+        self.path = path;
+    }
+
+    /// Creates the project directory with a .gitignore of the patterns (all
+    /// files, "*", if empty). An existing .gitignore is kept, so repeated
+    /// calls are harmless
+    pub fn scaffold(&self, gitignore_patterns: &[&str]) -> io::Result<()> {
+        self.scaffold_with_overwrite(gitignore_patterns, false)
+    }
+
+    /// Creates the project directory as scaffold, replacing an existing
+    /// .gitignore if overwrite is set
+    pub fn scaffold_with_overwrite(
+        &self,
+        gitignore_patterns: &[&str],
+        overwrite: bool,
+    ) -> io::Result<()> {
+        let root = Path::new(&self.path);
+        self.retry.run(|| {
+            self.file_system()
+                .create_dir_all(root, fs::DirBuilder::new().recursive(true))
+        })?;
+
+        let gitignore = root.join(".gitignore");
+        if self.file_system().exists(&gitignore) && !overwrite {
+            return Ok(());
+        }
+
+        let patterns = match gitignore_patterns {
+            [] => &["*"],
+            patterns => patterns,
+        };
+
+        let mut contents = patterns.join("\n");
+        contents.push('\n');
+        self.file_system()
+            .open(
+                &gitignore,
+                OpenOptions::new().create(true).truncate(true).write(true),
+            )?
+            .write_all(contents.as_bytes())
+    }
+
+    /// Initializes output files
+    ///
+    /// Files without an extension get the project extension; files with
+    /// their own extension keep it unless `force_extension` is set. Without
+    /// a project extension every file keeps its own.
+    pub fn initialize_output_files<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a mut FileManager>,
+    ) -> Result<(), String> {
+        self.initialize_output_files_with_progress(files, |_| ())
+    }
+
+    /// Initializes output files (as initialize_output_files), reporting the
+    /// progress of the initialization to the callback
+    pub fn initialize_output_files_with_progress<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a mut FileManager>,
+        mut on_progress: impl FnMut(ProgressEvent),
+    ) -> Result<(), String> {
+        let files: Vec<&mut FileManager> = files
+            .into_iter()
+            .map(|file| self.resolve_file(file))
+            .collect();
+
+        // Check for collisions before modifying the filesystem
+        Self::check_duplicates(&files)?;
+
+        let total = files.iter().map(|file| file.member_paths().len()).sum();
+        on_progress(ProgressEvent::Started { total });
+
+        let mut index = 0;
+        let mut on_created = |path: &Path| {
+            on_progress(ProgressEvent::FileCreated {
+                path: path.to_path_buf(),
+                index,
+                total,
+            });
+            index += 1;
+        };
+
+        files.into_iter().try_for_each(|file| {
+            self.try_initialize_output(file, &mut on_created)?;
+            self.run_post_init(file)
+        })?;
+
+        on_progress(ProgressEvent::Finished);
+        Ok(())
+    }
+
+    /// Initializes the output files of several projects (e.g. one per
+    /// experiment), the overwrite rules of each project apply to its own
+    /// files. A failure in one project does not stop the others, the results
+    /// are paired with the project paths in the order of the projects (a
+    /// root shared by several projects has several results)
+    pub fn initialize_many<'a>(
+        projects: impl IntoIterator<
+            Item = (&'a ProjectManager, Vec<&'a mut FileManager>),
+        >,
+    ) -> Vec<(String, Result<(), String>)> {
+        projects
+            .into_iter()
+            .map(|(project, files)| {
+                (project.path.clone(), project.initialize_output_files(files))
+            })
+            .collect()
+    }
+
+    /// Returns the total expected size (in bytes) of the files, counting
+    /// every file in series (files without a size hint are not counted). The
+    /// total saturates at u64::MAX instead of overflowing
+    pub fn total_expected_size(files: &[&FileManager]) -> u64 {
+        files
+            .iter()
+            .filter_map(|file| {
+                let size = file.reserve_hint()?;
+                Some(size.saturating_mul(u64::from(
+                    file.series_count().unwrap_or(1),
+                )))
+            })
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Fails if the filesystem of the project directory does not have the
+    /// space for the total expected size of the files
+    #[cfg(feature = "fs2")]
+    pub fn check_disk_space(
+        &self,
+        files: &[&FileManager],
+    ) -> Result<(), Error> {
+        let required = Self::total_expected_size(files);
+
+        // The project directory may not be created yet
+        let path = Path::new(&self.path)
+            .ancestors()
+            .find(|dir| dir.is_dir())
+            .unwrap_or(Path::new("."));
+        let available = fs2::available_space(path)?;
+
+        if required > available {
+            return Err(Error::InsufficientSpace {
+                path: path.to_path_buf(),
+                required,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the existing paths (including series members) that would be
+    /// affected by initializing the files, without modifying anything
+    pub fn conflicts(&self, files: &[&FileManager]) -> Vec<PathBuf> {
+        files
+            .iter()
+            .flat_map(|file| {
+                let mut file = (*file).clone();
+
+                // Files with an incomplete path can't conflict with anything
+                match self.resolve_file(&mut file).path {
+                    Some(_) => file.member_paths(),
+                    None => Vec::new(),
+                }
+            })
+            .filter(|path| self.file_system().exists(path))
+            .collect()
+    }
+
+    /// Applies the project settings to the file and calculates its path
+    fn resolve_file<'a>(
+        &self,
+        file: &'a mut FileManager,
+    ) -> &'a mut FileManager {
+        if let Some(extension) = &self.extension {
+            if self.force_extension && !file.initialized() {
+                file.descriptor.extension = Some(extension.clone());
             }
+            file.set_extension(extension);
         }
+        file.set_project_path(&self.output_root()).set_path()
     }
 
-    /// Helper method for initializing a single (new) file
-    fn initialize_file(path: &Path, header: &Option<String>) {
-        match OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(path)
-        {
-            // Write the header
-            Ok(mut file) => match header {
-                Some(header_str) => {
-                    if let Err(reason) = writeln!(file, "{header_str}") {
-                        panic!(
-                            "Could not write to file {:?}: {:?}",
-                            path, reason
-                        );
+    /// Returns the existing files under the project directory matching the
+    /// glob pattern (relative to the project path, e.g. "**/*.dat") as
+    /// attached FileManagers, in the alphabetical order of the paths
+    #[cfg(feature = "glob")]
+    pub fn discover(&self, pattern: &str) -> io::Result<Vec<FileManager>> {
+        let invalid_data =
+            |reason: String| io::Error::new(io::ErrorKind::InvalidData, reason);
+
+        let root = Path::new(&self.path);
+        let full_pattern =
+            format!("{}/{pattern}", glob::Pattern::escape(&self.path));
+
+        let paths = glob::glob(&full_pattern).map_err(|reason| {
+            io::Error::new(io::ErrorKind::InvalidInput, reason)
+        })?;
+
+        let mut files = Vec::new();
+        for path in paths {
+            let path = path.map_err(io::Error::from)?;
+            if !path.is_file() {
+                continue;
+            }
+
+            let relative = path.strip_prefix(root).map_err(|_| {
+                invalid_data(format!("{path:?} is outside of the project"))
+            })?;
+            let to_str = |component: &std::ffi::OsStr| {
+                component.to_str().map(str::to_string).ok_or_else(|| {
+                    invalid_data(format!("{path:?} is not valid unicode"))
+                })
+            };
+
+            let output_path = match relative.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    to_str(parent.as_os_str())?
+                }
+                _ => String::from("."),
+            };
+            let name = to_str(path.file_stem().unwrap_or_default())?;
+            let extension = to_str(path.extension().unwrap_or_default())?;
+
+            let mut file = FileManager::default()
+                .set_project_path(&self.path)
+                .set_output_path(&output_path)
+                .set_file_name(&name)
+                .set_extension(&extension)
+                .build();
+            file.attach_existing()
+                .map_err(|reason| invalid_data(reason.to_string()))?;
+
+            files.push(file);
+        }
+
+        Ok(files)
+    }
+
+    /// Verifies that no two files (including series members) resolve to the
+    /// same path
+    fn check_duplicates(files: &[&mut FileManager]) -> Result<(), String> {
+        let mut owners: HashMap<PathBuf, usize> = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for (i, file) in files.iter().enumerate() {
+            for path in file.member_paths() {
+                match owners.get(&path) {
+                    Some(&owner) => conflicts
+                        .push(format!("{path:?} (files #{owner} and #{i})")),
+                    None => {
+                        owners.insert(path, i);
                     }
                 }
-                None => (),
-            },
-            Err(reason) => {
-                panic!("Could not open file {:?}: {:?}", path, reason)
             }
         }
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Duplicate output paths: {}.", conflicts.join(", ")))
+        }
     }
 
-    // Write methods
+    /// Attempts to initialize output files depending on the overwrite
+    /// conditions
+    fn try_initialize_output(
+        &self,
+        file: &mut FileManager,
+        on_created: &mut dyn FnMut(&Path),
+    ) -> Result<(), String> {
+        // Standard streams can't be overwritten
+        if file.is_stream() {
+            file.initialize_output();
+            return Ok(());
+        }
 
-    /// Returns write permission of the file manager
-    pub fn writable(&self) -> bool {
-        self.writable
-    }
+        // The file keeps using the filesystem of the project (e.g. rotation)
+        if let (None, Some(file_system)) =
+            (&file.file_system, &self.file_system)
+        {
+            file.file_system = Some(SharedFileSystem(Arc::clone(file_system)));
+        }
+
+        let file_system = self.file_system();
+
+        // The existing outputs of a staged project are the published ones
+        let published = self.published_path(file.path());
+        let exists =
+            file_system.exists(file.path()) || file_system.exists(&published);
+
+        match file.overwrite_type().unwrap_or(self.overwrite_type) {
+            OverwriteType::Panic => {
+                if exists {
+                    Err(String::from(
+                        "Permission denied to overwrite existing output files.",
+                    ))
+                } else {
+                    file.initialize_output_with(
+                        on_created,
+                        &self.retry,
+                        self.file_system(),
+                        false,
+                    );
+                    Ok(())
+                }
+            }
+            OverwriteType::Archive => {
+                if file_system.exists(&published) {
+                    self.archive_file(&published).map_err(|reason| {
+                        format!("Cannot archive file {published:?}: {reason}")
+                    })?;
+                }
+
+                file.initialize_output_with(
+                    on_created,
+                    &self.retry,
+                    self.file_system(),
+                    false,
+                );
+                Ok(())
+            }
+            OverwriteType::Overwrite => {
+                file.initialize_output_with(
+                    on_created,
+                    &self.retry,
+                    self.file_system(),
+                    false,
+                );
+                Ok(())
+            }
+            OverwriteType::SkipIfUnchanged => {
+                self.stage_published(file.path(), &published)?;
+                file.initialize_output_with(
+                    on_created,
+                    &self.retry,
+                    self.file_system(),
+                    true,
+                );
+                Ok(())
+            }
+            OverwriteType::Ignore => {
+                if exists {
+                    // The kept file has to survive the publication
+                    self.stage_published(file.path(), &published)?;
+                    file.change_write_permission(false);
+                } else {
+                    file.initialize_output_with(
+                        on_created,
+                        &self.retry,
+                        self.file_system(),
+                        false,
+                    );
+                }
+                Ok(())
+            }
+            OverwriteType::None => {
+                if !file.initialized() {
+                    file.set_path();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the path a file initialized in the staging directory has once
+    /// published (the path itself if the project is not staged)
+    fn published_path(&self, path: &Path) -> PathBuf {
+        if self.staged {
+            let staging = self.staging_path();
+            let relative = path.strip_prefix(&staging).ok().or_else(|| {
+                path.strip_prefix(staging.canonicalize().ok()?).ok()
+            });
+            if let Some(relative) = relative {
+                return Path::new(&self.path).join(relative);
+            }
+        }
+        path.to_path_buf()
+    }
+
+    /// Copies the published version of a staged file to the staging
+    /// directory (if the staged file does not exist yet), so that a file
+    /// kept as it is remains in the project once the staging tree is
+    /// published
+    fn stage_published(
+        &self,
+        path: &Path,
+        published: &Path,
+    ) -> Result<(), String> {
+        let file_system = self.file_system();
+        if path == published
+            || file_system.exists(path)
+            || !file_system.exists(published)
+        {
+            return Ok(());
+        }
+
+        let stage = || -> io::Result<()> {
+            if let Some(parent) = path.parent() {
+                self.retry.run(|| {
+                    file_system.create_dir_all(
+                        parent,
+                        fs::DirBuilder::new().recursive(true),
+                    )
+                })?;
+            }
+            file_system.copy(published, path).map(|_| ())
+        };
+        stage().map_err(|reason| {
+            format!("Cannot stage the published file {published:?}: {reason}")
+        })
+    }
+
+    /// Calls the post-initialization hook with a file that was initialized
+    fn run_post_init(&self, file: &FileManager) -> Result<(), String> {
+        match &self.post_init {
+            Some(post_init) if file.writable() => {
+                post_init(file).map_err(|reason| {
+                    format!("Post-initialization of {file} failed: {reason}")
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the path a file is archived to (archive/<output
+    /// directory>/<file name> in the project directory, the published one
+    /// for a staged project), the file system is not accessed
+    pub fn archive_destination(&self, file_path: &Path) -> PathBuf {
+        let mut destination = Path::new(&self.path).join("archive");
+
+        if let Some(directory) =
+            file_path.parent().and_then(|parent| parent.file_name())
+        {
+            destination.push(directory);
+        }
+        if let Some(name) = file_path.file_name() {
+            destination.push(name);
+        }
+
+        destination
+    }
+
+    /// Copies a file to the archive (or archives it with the archive
+    /// function) and returns its destination
+    pub fn archive_file(&self, file_path: &Path) -> io::Result<PathBuf> {
+        if file_path.file_name().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no file name in path {file_path:?}"),
+            ));
+        }
+
+        let destination = self.archive_destination(file_path);
+
+        let file_system = self.file_system();
+        if let Some(directory) = destination.parent() {
+            if !file_system.exists(directory) {
+                self.retry.run(|| {
+                    file_system.create_dir_all(
+                        directory,
+                        fs::DirBuilder::new().recursive(true),
+                    )
+                })?;
+            }
+        }
+
+        match &self.archive_fn {
+            Some(archive_fn) => archive_fn(file_path, &destination)?,
+            None => {
+                file_system.copy(file_path, &destination)?;
+            }
+        }
+
+        Ok(destination)
+    }
+}
+
+impl fmt::Display for ProjectManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut summary = match &self.extension {
+            Some(extension) => format!(
+                "Project path is {}\nOutput files have extension \
+                .{extension}\n",
+                self.path
+            ),
+            None => format!(
+                "Project path is {}\nOutput files keep their own \
+                extensions\n",
+                self.path
+            ),
+        };
+        summary.push_str("Existing output files ");
+
+        match &self.overwrite_type {
+            OverwriteType::Panic => summary.push_str(
+                "will interrupt the program (overwrite_typ = Panic).\n",
+            ),
+            OverwriteType::Archive => summary
+                .push_str("will be archived (overwrite_type = Archive).\n"),
+            OverwriteType::Overwrite => summary.push_str(
+                "will be overwritten (overwrite_type = Overwrite).\n",
+            ),
+            OverwriteType::SkipIfUnchanged => summary.push_str(
+                "will be kept if unchanged and overwritten otherwise \
+                (overwrite_type = SkipIfUnchanged).\n",
+            ),
+            OverwriteType::Ignore => summary.push_str(
+                "will not be collected again during this run \
+                (overwirte_type = Ignore).\n",
+            ),
+            OverwriteType::None => summary.push_str(
+                "will not be touched, no files are created \
+                (overwrite_type = None).\n",
+            ),
+        }
+
+        write!(f, "{summary}")
+    }
+}
+
+/// Resolved output plan of a project (see ProjectManager::plan), serialized
+/// e.g. as a single JSON log line
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ProjectPlan {
+    // Path to the project directory
+    project_path: String,
+    // Output file extension
+    extension: Option<String>,
+    // Type of behaviour if project files already exist
+    overwrite_type: OverwriteType,
+    // Resolved output file(s) of each FileManager
+    files: Vec<PlannedFile>,
+}
+
+/// Resolved output file(s) of a single FileManager in a ProjectPlan
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PlannedFile {
+    // Destination of the data
+    target: Target,
+    // Resolved paths (all members if dealing with series)
+    paths: Vec<PathBuf>,
+    // Paths that already exist and are subject to the overwrite type
+    existing: Vec<PathBuf>,
+    // Treatment of the existing paths (the one of the file if it overrides
+    // the project)
+    overwrite_type: OverwriteType,
+    // Column descriptions in the output file(s)
+    header: Option<String>,
+}
+
+impl ProjectPlan {
+    /// Returns the path to the project directory
+    pub fn project_path(&self) -> &str {
+        &self.project_path
+    }
+
+    /// Returns the planned files
+    pub fn files(&self) -> &[PlannedFile] {
+        &self.files
+    }
+}
+
+impl PlannedFile {
+    /// Returns the resolved paths
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns the resolved paths that already exist
+    pub fn existing(&self) -> &[PathBuf] {
+        &self.existing
+    }
+
+    /// Returns the treatment of the existing paths
+    pub fn overwrite_type(&self) -> OverwriteType {
+        self.overwrite_type
+    }
+}
+
+impl fmt::Display for ProjectPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extension = match &self.extension {
+            Some(extension) => format!("extension .{extension}"),
+            None => String::from("own extensions"),
+        };
+        writeln!(
+            f,
+            "Project path is {} ({extension}, overwrite_type = {:?})",
+            self.project_path, self.overwrite_type
+        )?;
+
+        for file in &self.files {
+            match file.target {
+                Target::Stdout => writeln!(f, "  <stdout>")?,
+                Target::Stderr => writeln!(f, "  <stderr>")?,
+                Target::File if file.paths.is_empty() => {
+                    writeln!(f, "  <unresolved path>")?
+                }
+                Target::File => {
+                    // Files overriding the overwrite type of the project
+                    // show their own
+                    let overwrite_type = (file.overwrite_type
+                        != self.overwrite_type)
+                        .then(|| {
+                            format!(
+                                "overwrite_type = {:?}",
+                                file.overwrite_type
+                            )
+                        });
+
+                    for path in &file.paths {
+                        let status: Vec<&str> = file
+                            .existing
+                            .contains(path)
+                            .then_some("exists")
+                            .into_iter()
+                            .chain(overwrite_type.as_deref())
+                            .collect();
+                        match status.as_slice() {
+                            [] => writeln!(f, "  {}", path.display())?,
+                            status => writeln!(
+                                f,
+                                "  {} ({})",
+                                path.display(),
+                                status.join(", ")
+                            )?,
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProjectManager {
+    /// Returns the output plan of the files (resolved paths, existing files)
+    /// without modifying anything
+    pub fn plan(&self, files: &[&FileManager]) -> ProjectPlan {
+        let files = files
+            .iter()
+            .map(|file| {
+                let mut file = (*file).clone();
+
+                let paths = match self.resolve_file(&mut file).path {
+                    Some(_) => file.member_paths(),
+                    None => Vec::new(),
+                };
+                let existing = paths
+                    .iter()
+                    .filter(|path| self.file_system().exists(path))
+                    .cloned()
+                    .collect();
+
+                PlannedFile {
+                    target: file.descriptor.target.unwrap_or(Target::File),
+                    paths,
+                    existing,
+                    overwrite_type: file
+                        .overwrite_type()
+                        .unwrap_or(self.overwrite_type),
+                    header: file.descriptor.header.clone(),
+                }
+            })
+            .collect();
+
+        ProjectPlan {
+            project_path: self.path.clone(),
+            extension: self.extension.clone(),
+            overwrite_type: self.overwrite_type,
+            files,
+        }
+    }
+
+    /// Writes a JSON manifest of the output files to the specified path
+    pub fn write_manifest(
+        &self,
+        files: &[&FileManager],
+        path: &Path,
+    ) -> io::Result<()> {
+        let manifest = Manifest {
+            project_path: self.path.clone(),
+            entries: files
+                .iter()
+                .map(|file| ManifestEntry::try_from(*file))
+                .collect::<io::Result<Vec<_>>>()?,
+        };
+
+        let mut file = self.file_system().open(
+            path,
+            OpenOptions::new().create(true).truncate(true).write(true),
+        )?;
+        serde_json::to_writer_pretty(&mut file, &manifest)?;
+        writeln!(file)
+    }
+}
+
+/// Machine-readable record of the output files of a project
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Manifest {
+    // Path to the project directory
+    project_path: String,
+    // Description of each FileManager
+    entries: Vec<ManifestEntry>,
+}
+
+/// Description of the output file(s) of a single FileManager
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    // Column descriptions in the output file(s)
+    header: Option<String>,
+    // Number of files in series
+    series: Option<u32>,
+    // Output files (all members if dealing with series)
+    files: Vec<ManifestFile>,
+}
+
+/// Location and size of a single output file
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ManifestFile {
+    // Resolved path of the file
+    path: PathBuf,
+    // Size of the file in bytes
+    size: u64,
+    // SHA-256 of the contents (requires the checksum feature)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+}
+
+impl Manifest {
+    /// Reads a manifest written by ProjectManager::write_manifest
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Returns the path to the project directory
+    pub fn project_path(&self) -> &str {
+        &self.project_path
+    }
+
+    /// Returns the descriptions of the FileManagers
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+}
+
+impl ManifestEntry {
+    /// Returns the header of the output file(s)
+    pub fn header(&self) -> Option<&str> {
+        self.header.as_deref()
+    }
+
+    /// Returns the number of files in series
+    pub fn series(&self) -> Option<u32> {
+        self.series
+    }
+
+    /// Returns the output files
+    pub fn files(&self) -> &[ManifestFile] {
+        &self.files
+    }
+}
+
+impl TryFrom<&FileManager> for ManifestEntry {
+    type Error = io::Error;
+
+    fn try_from(file: &FileManager) -> io::Result<Self> {
+        Ok(Self {
+            header: file.descriptor.header.clone(),
+            series: file.descriptor.series.map(|series| series.count),
+            files: file
+                .member_paths()
+                .into_iter()
+                .map(|path| {
+                    let size = file.file_system().metadata(&path)?.len();
+
+                    #[cfg(feature = "checksum")]
+                    let checksum = Some(sha256_hex(&path)?);
+                    #[cfg(not(feature = "checksum"))]
+                    let checksum = None;
+
+                    Ok(ManifestFile {
+                        path,
+                        size,
+                        checksum,
+                    })
+                })
+                .collect::<io::Result<Vec<_>>>()?,
+        })
+    }
+}
+
+impl ManifestFile {
+    /// Returns the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the SHA-256 of the file if it was recorded
+    pub fn checksum(&self) -> Option<&str> {
+        self.checksum.as_deref()
+    }
+}
+
+/// Option to remove the file(s) of a manager when it is dropped, reset in the
+/// clones so that dropping a clone keeps the file(s) of the original
+#[derive(Debug, Default, PartialEq)]
+struct CleanupOnDrop(bool);
+
+impl Clone for CleanupOnDrop {
+    fn clone(&self) -> Self {
+        Self(false)
+    }
+}
+
+/// Type for output file manipulation, (de)serialized as its FileDescriptor
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "FileDescriptor", into = "FileDescriptor")]
+pub struct FileManager {
+    // Logical fields defining the file(s)
+    descriptor: FileDescriptor,
+    // Absolute path of the output file
+    path: Option<PathBuf>,
+    // Permission for writing to the file
+    writable: bool,
+    // Option to remove the file(s) when the manager is dropped (not copied
+    // to the clones)
+    cleanup_on_drop: CleanupOnDrop,
+    // Directories created by the initialization (outermost first)
+    created_dirs: Vec<PathBuf>,
+    // Filesystem of the output file(s) (std::fs if not set)
+    file_system: Option<SharedFileSystem>,
+}
+
+/// Serialized form of a FileManager: the logical fields defining the
+/// file(s), embedded by the manager next to its runtime state (resolved
+/// path, write permission, cleanup option and created directories). The
+/// field names are kept stable for embedding file definitions in other
+/// serialized structures
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct FileDescriptor {
+    // Column descriptions in the output file
+    pub header: Option<String>,
+    // Project path
+    pub project_path: Option<String>,
+    // Output path (relative to the project path)
+    pub output_path: Option<String>,
+    // File name
+    pub name: Option<String>,
+    // File extension
+    pub extension: Option<String>,
+    // Option for a series of data files with related name/structure
+    pub series: Option<Series>,
+    // Distinct headers of the files in series (by file index)
+    pub series_headers: Option<Vec<String>>,
+    // Metadata lines written before the header (units, run parameters, ...)
+    pub preamble: Option<Vec<String>>,
+    // Prefix of the preamble and header lines (e.g. '#' for numpy.loadtxt)
+    pub comment_char: Option<char>,
+    // Permissions of the created files (Unix only)
+    pub mode: Option<u32>,
+    // Permissions of the created directories (Unix only)
+    pub dir_mode: Option<u32>,
+    // Field delimiter of CSV records
+    pub delimiter: Option<u8>,
+    // Layout of fixed-width columns (see write_columns)
+    pub columns: Option<ColumnSpec>,
+    // Size (in bytes) after which the file is rotated
+    pub max_size: Option<u64>,
+    // Expected size (in bytes) of the file once written
+    pub expected_size: Option<u64>,
+    // Terminator of the written lines (the platform default if not set)
+    pub line_ending: Option<LineEnding>,
+    // Option to sync the file to the disk after every appended line
+    pub sync_on_append: Option<bool>,
+    // Option to start the file with the UTF-8 byte order mark
+    pub write_bom: Option<bool>,
+    // Option to canonicalize the path (enabled if not set)
+    pub canonicalize: Option<bool>,
+    // Option to make file names portable (enabled on Windows if not set)
+    pub sanitize_names: Option<bool>,
+    // Option to refuse names changed by the sanitization in try_build
+    pub strict_names: Option<bool>,
+    // Option to require the project directory to exist when building
+    pub strict_project_path: Option<bool>,
+    // Option to resolve a relative project path against the directory of
+    // the config file (instead of the current directory)
+    pub resolve_relative_to_config: Option<bool>,
+    // Compression codec of the output file
+    pub compression: Option<Compression>,
+    // Destination of the data (a file if not set)
+    pub target: Option<Target>,
+    // Treatment of an existing file overriding the one of the project
+    pub overwrite_type: Option<OverwriteType>,
+    // Option to refuse initializing files locked by another process
+    pub lock_on_init: Option<bool>,
+}
+
+impl From<&FileManager> for FileDescriptor {
+    fn from(file: &FileManager) -> Self {
+        file.descriptor.clone()
+    }
+}
+
+impl From<FileManager> for FileDescriptor {
+    fn from(file: FileManager) -> Self {
+        FileDescriptor::from(&file)
+    }
+}
+
+/// Returns an unbuilt manager of the file(s), fails if the distinct headers
+/// do not cover the series. A relative project path is resolved against the
+/// directory of the config file being deserialized (if any) when
+/// resolve_relative_to_config is set
+impl TryFrom<FileDescriptor> for FileManager {
+    type Error = Error;
+
+    fn try_from(descriptor: FileDescriptor) -> Result<Self, Error> {
+        let project_path = match (
+            descriptor.resolve_relative_to_config,
+            descriptor.project_path,
+        ) {
+            (Some(true), Some(project_path)) => Some(relative_to_config(
+                &project_path,
+                deserialized_source_path().as_deref(),
+            )),
+            (_, project_path) => project_path,
+        };
+
+        let file = FileManager {
+            descriptor: FileDescriptor {
+                project_path,
+                extension: descriptor.extension.map(|extension| {
+                    without_leading_dot(&extension).to_string()
+                }),
+                ..descriptor
+            },
+            path: None,
+            writable: false,
+            cleanup_on_drop: CleanupOnDrop::default(),
+            created_dirs: Vec::new(),
+            file_system: None,
+        };
+
+        file.check_series_headers()?;
+        Ok(file)
+    }
+}
+
+impl FileManager {
+    // Constructors
+
+    /// Creates a new file with a unique name in the system temp directory,
+    /// enable change_cleanup_on_drop to remove it when the manager is dropped
+    pub fn temp(extension: &str) -> Result<FileManager, Error> {
+        let temp_dir = std::env::temp_dir();
+        let project_path = temp_dir.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Temp directory {temp_dir:?} is not valid unicode"),
+            )
+        })?;
+
+        let name = unique_name("io_utils");
+
+        let mut file = FileManager::default()
+            .set_project_path(project_path)
+            .set_output_path(".")
+            .set_file_name(&name)
+            .set_extension(extension)
+            .build();
+
+        file.create_file(&RealFileSystem)?;
+        file.path = Some(file.path().canonicalize()?);
+        file.writable = true;
+
+        Ok(file)
+    }
+
+    /// Extracts the file manager from a table of the config, a relative
+    /// project path is resolved against the directory of the config file if
+    /// resolve_relative_to_config is set (as with the other functions of
+    /// config_parse, e.g. for nested file managers)
+    pub fn from_config(
+        config: &Config,
+        table_name: &str,
+    ) -> Result<Self, Error> {
+        <Self as FromConfig>::try_from_config(config, table_name)
+    }
+
+    /// Extracts file managers from an array of tables of the config, e.g.
+    ///
+    /// ```
+    /// use io_utils::config_parse::Config;
+    /// use io_utils::files::FileManager;
+    ///
+    /// let config = Config::from_toml_str(
+    ///     r#"
+    ///     [[outputs]]
+    ///     output_path = "energy"
+    ///     name = "energy"
+    ///     header = "time energy"
+    ///
+    ///     [[outputs]]
+    ///     output_path = "snapshots"
+    ///     name = "snapshot"
+    ///     series = 10
+    ///     "#,
+    /// );
+    ///
+    /// let files = FileManager::from_config_seq(&config, "outputs").unwrap();
+    /// assert_eq!(2, files.len());
+    /// ```
+    ///
+    /// The files are initialized by ProjectManager::initialize_output_files
+    /// as usual, the runtime state (path, write permission) starts unset.
+    pub fn from_config_seq(
+        config: &Config,
+        key: &str,
+    ) -> Result<Vec<Self>, Error> {
+        from_config_seq(config, key)
+    }
+
+    /// Creates a FileManager writing to the standard output
+    pub fn stdout() -> FileManager {
+        FileManager::default().set_target(Target::Stdout).clone()
+    }
+
+    /// Creates a FileManager writing to the standard error
+    pub fn stderr() -> FileManager {
+        FileManager::default().set_target(Target::Stderr).clone()
+    }
+
+    /// Creates a FileManager for the full path to a file, the path is split
+    /// into the project path (parent of the parent directory), the output
+    /// path (parent directory), the file name, and the extension
+    pub fn from_full_path(path: &Path) -> Result<FileManager, Error> {
+        let invalid_path = |reason: &str| Error::InvalidPath {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        };
+        let to_string = |component: &std::ffi::OsStr| {
+            component
+                .to_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid_path("not valid unicode"))
+        };
+
+        let name = path
+            .file_stem()
+            .ok_or_else(|| invalid_path("no file name"))
+            .and_then(to_string)?;
+        let extension = path
+            .extension()
+            .ok_or_else(|| invalid_path("no file extension"))
+            .and_then(to_string)?;
+
+        let output_dir = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .ok_or_else(|| invalid_path("no parent directory"))?;
+        let output_path = output_dir
+            .file_name()
+            .ok_or_else(|| invalid_path("no output directory name"))
+            .and_then(to_string)?;
+
+        let project_path = match output_dir.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                to_string(parent.as_os_str())?
+            }
+            _ => String::from("."),
+        };
+
+        Ok(FileManager::default()
+            .set_project_path(&project_path)
+            .set_output_path(&output_path)
+            .set_file_name(&name)
+            .set_extension(&extension)
+            .build())
+    }
+
+    /// Creates a FileManager for an existing file (decomposed as in
+    /// from_full_path), the data is appended without truncating the file
+    pub fn attach(path: &Path) -> Result<FileManager, Error> {
+        let mut file = Self::from_full_path(path)?;
+        file.attach_existing()?;
+        Ok(file)
+    }
+
+    /// Helper method marking the existing file at the built path as writable
+    fn attach_existing(&mut self) -> Result<(), Error> {
+        if !self.path().is_file() {
+            return Err(Error::InvalidPath {
+                path: self.path().clone(),
+                reason: String::from("file does not exist"),
+            });
+        }
+
+        self.writable = true;
+        Ok(())
+    }
+
+    // Builder methods
+
+    /// Return the initialization state
+    pub fn initialized(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Returns the manager to the unbuilt state, keeping the other fields as
+    /// a template for the Setters (which still only fill the unset fields).
+    /// The file on disk is left untouched and is no longer removed on drop.
+    pub fn reset(&mut self) -> &mut Self {
+        self.path = None;
+        self.writable = false;
+        self.cleanup_on_drop = CleanupOnDrop(false);
+        self
+    }
+
+    /// Checks whether both managers describe the same file(s), ignoring the
+    /// runtime state (resolved path, write permission, cleanup option,
+    /// created directories and the current index in series)
+    pub fn same_config(&self, other: &FileManager) -> bool {
+        let definition = |file: &FileManager| {
+            let mut file = file.clone();
+            file.reset();
+            file.created_dirs.clear();
+            file.file_system = None;
+            if let Some(series) = &mut file.descriptor.series {
+                series.index = 0;
+            }
+            file
+        };
+
+        definition(self) == definition(other)
+    }
+
+    // Setters
+    // Note: the setters only work when self.initialized() = false, and
+    // the specified field has not been set yet.For all other cases,
+    // one must use Modifiers
+    //
+    // State machine of a FileManager:
+    //   unbuilt --build()/try_build()--> built (path set, Modifiers work)
+    //   built --initialize_output()--> initialized (files created, writable)
+    //   built/initialized --reset()--> unbuilt (Setters work again)
+
+    /// Sets the header, embedded newlines split it into several lines (a
+    /// trailing newline or "\r\n" line endings do not add lines)
+    pub fn set_header(&mut self, header: &str) -> &mut Self {
+        if !self.initialized() && self.descriptor.header.is_none() {
+            self.descriptor.header = Some(header.to_string());
+        }
+        self
+    }
+
+    /// Adds a path to the root project directory
+    pub fn set_project_path(&mut self, project_path: &str) -> &mut Self {
+        if !self.initialized() && self.descriptor.project_path.is_none() {
+            self.descriptor.project_path = Some(project_path.to_string());
+        }
+        self
+    }
+
+    /// Sets the output directory path, relative to the project directory. An
+    /// absolute path (e.g. "/var/log/app") takes precedence over the project
+    /// directory: the file is written there whatever the project path is
+    /// (also for the staged outputs of a project)
+    pub fn set_output_path(&mut self, output_path: &str) -> &mut Self {
+        if !self.initialized() && self.descriptor.output_path.is_none() {
+            self.descriptor.output_path = Some(output_path.to_string());
+        }
+        self
+    }
+
+    /// Sets the name of the output file
+    pub fn set_file_name(&mut self, name: &str) -> &mut Self {
+        if !self.initialized() && self.descriptor.name.is_none() {
+            self.descriptor.name = Some(name.to_string());
+        }
+        self
+    }
+
+    /// Sets the extension of the ouput file ("dat" or ".dat")
+    pub fn set_extension(&mut self, extension: &str) -> &mut Self {
+        if !self.initialized() && self.descriptor.extension.is_none() {
+            self.descriptor.extension =
+                Some(without_leading_dot(extension).to_string());
+        }
+        self
+    }
+
+    /// Sets the number of files in series
+    pub fn set_series(&mut self, n_files: u32) -> &mut Self {
+        if !self.initialized() && self.descriptor.series.is_none() {
+            self.descriptor.series = Some(Series::new(n_files));
+        }
+        self
+    }
+
+    /// Sets the number of files in series from the existing name_N.ext files
+    /// in the output directory (before building, e.g. to resume a series),
+    /// and returns it. Missing files in the numbering are reported as a
+    /// warning, the count still covers the largest number found
+    pub fn scan_series(&mut self) -> io::Result<u32> {
+        if self.initialized() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot scan the series of a built file manager",
+            ));
+        }
+
+        let start = self.descriptor.series.map_or(0, |series| series.start);
+
+        // The file names of two members differ only in the number
+        let mut probe = self.clone();
+        probe.descriptor.series = Some(Series {
+            count: 2,
+            index: 0,
+            start: 0,
+        });
+        let first = probe.calculate_path();
+        probe.descriptor.series = Some(Series {
+            count: 2,
+            index: 1,
+            start: 0,
+        });
+        let second = probe.calculate_path();
+
+        let (first, second) = match (first, second) {
+            (Some(first), Some(second)) => (first, second),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The path of the series is not defined",
+                ))
+            }
+        };
+
+        let dir = first.parent().unwrap_or(Path::new(""));
+        let first = first.file_name().unwrap_or_default().to_string_lossy();
+        let second = second.file_name().unwrap_or_default().to_string_lossy();
+        let prefix_len = first
+            .bytes()
+            .zip(second.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (prefix, suffix) = (&first[..prefix_len], &first[prefix_len + 1..]);
+
+        // Numbers of the existing members
+        let mut numbers = Vec::new();
+        let file_system = self.file_system();
+        if file_system.metadata(dir).is_ok_and(|meta| meta.is_dir()) {
+            for entry in file_system.read_dir(dir)? {
+                let file_name = entry?.file_name();
+                let number = file_name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix(prefix))
+                    .and_then(|name| name.strip_suffix(suffix))
+                    .filter(|number| {
+                        number.bytes().all(|c| c.is_ascii_digit())
+                            && !(number.len() > 1 && number.starts_with('0'))
+                    })
+                    .and_then(|number| number.parse::<usize>().ok());
+
+                if let Some(number) = number.filter(|number| *number >= start) {
+                    numbers.push(number);
+                }
+            }
+        }
+        numbers.sort_unstable();
+
+        let count = numbers.last().map_or(0, |last| last + 1 - start);
+        if numbers.len() < count {
+            let missing: Vec<usize> = (start..start + count)
+                .filter(|number| numbers.binary_search(number).is_err())
+                .collect();
+            log::warn!(
+                "Missing file(s) {missing:?} in series {prefix}N{suffix} in \
+                {dir:?}"
+            );
+        }
+
+        let count = u32::try_from(count).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Too many files in series {prefix}N{suffix}"),
+            )
+        })?;
+
+        self.descriptor.series = Some(Series {
+            count,
+            index: 0,
+            start,
+        });
+        Ok(count)
+    }
+
+    /// Sets a distinct header for each file in series (by file index), used
+    /// instead of the common header. try_build fails if a file has no header
+    /// (build leaves the file without a header)
+    pub fn set_series_headers(&mut self, headers: Vec<String>) -> &mut Self {
+        if !self.initialized() && self.descriptor.series_headers.is_none() {
+            self.descriptor.series_headers = Some(headers);
+        }
+        self
+    }
+
+    /// Sets the metadata lines written before the header, one line each
+    pub fn set_preamble(&mut self, lines: Vec<String>) -> &mut Self {
+        if !self.initialized() && self.descriptor.preamble.is_none() {
+            self.descriptor.preamble = Some(lines);
+        }
+        self
+    }
+
+    /// Sets the character prefixing every preamble and header line
+    pub fn set_comment_char(&mut self, comment_char: char) -> &mut Self {
+        if !self.initialized() && self.descriptor.comment_char.is_none() {
+            self.descriptor.comment_char = Some(comment_char);
+        }
+        self
+    }
+
+    /// Sets the permissions of the output file, e.g. 0o640 (ignored on
+    /// non-Unix platforms)
+    pub fn set_mode(&mut self, mode: u32) -> &mut Self {
+        if !self.initialized() && self.descriptor.mode.is_none() {
+            self.descriptor.mode = Some(mode);
+        }
+        self
+    }
+
+    /// Sets the permissions of the created output directories (ignored on
+    /// non-Unix platforms)
+    pub fn set_dir_mode(&mut self, dir_mode: u32) -> &mut Self {
+        if !self.initialized() && self.descriptor.dir_mode.is_none() {
+            self.descriptor.dir_mode = Some(dir_mode);
+        }
+        self
+    }
+
+    /// Sets the field delimiter of CSV records (',' by default)
+    pub fn set_delimiter(&mut self, delimiter: u8) -> &mut Self {
+        if !self.initialized() && self.descriptor.delimiter.is_none() {
+            self.descriptor.delimiter = Some(delimiter);
+        }
+        self
+    }
+
+    /// Sets the layout of fixed-width columns written by write_columns, the
+    /// whitespace-separated names of the header are aligned to the same
+    /// widths (after the comment character if one is set)
+    pub fn set_columns(&mut self, columns: &ColumnSpec) -> &mut Self {
+        if !self.initialized() && self.descriptor.columns.is_none() {
+            self.descriptor.columns = Some(columns.clone());
+        }
+        self
+    }
+
+    /// Returns the layout of fixed-width columns
+    pub fn columns(&self) -> Option<&ColumnSpec> {
+        self.descriptor.columns.as_ref()
+    }
+
+    /// Sets the size (in bytes) after which the file is rotated: the full
+    /// file is renamed to the next free numbered slot (file.1.dat,
+    /// file.2.dat, ...) and a new file is started with the header
+    pub fn set_max_size(&mut self, max_size: u64) -> &mut Self {
+        if !self.initialized() && self.descriptor.max_size.is_none() {
+            self.descriptor.max_size = Some(max_size);
+        }
+        self
+    }
+
+    /// Sets the expected size (in bytes) of the file once written (of each
+    /// file if dealing with series), used for the disk space checks
+    pub fn set_expected_size(&mut self, expected_size: u64) -> &mut Self {
+        if !self.initialized() && self.descriptor.expected_size.is_none() {
+            self.descriptor.expected_size = Some(expected_size);
+        }
+        self
+    }
+
+    /// Returns the expected size (in bytes) of the file once written
+    pub fn reserve_hint(&self) -> Option<u64> {
+        self.descriptor.expected_size
+    }
+
+    /// Sets the terminator of the header and appended lines (the platform
+    /// line ending by default)
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) -> &mut Self {
+        if !self.initialized() && self.descriptor.line_ending.is_none() {
+            self.descriptor.line_ending = Some(line_ending);
+        }
+        self
+    }
+
+    /// Returns the terminator of the written lines
+    pub fn line_ending(&self) -> LineEnding {
+        self.descriptor
+            .line_ending
+            .unwrap_or_else(LineEnding::native)
+    }
+
+    /// Sets whether append_line syncs the file to the disk after every line
+    /// (disabled by default). Every sync waits for the disk, so this slows
+    /// down frequent small writes considerably; it is meant for checkpoint
+    /// files that must survive a crash
+    pub fn set_sync_on_append(&mut self, sync_on_append: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.sync_on_append.is_none() {
+            self.descriptor.sync_on_append = Some(sync_on_append);
+        }
+        self
+    }
+
+    /// Sets whether the created file starts with the UTF-8 byte order mark
+    /// (disabled by default), e.g. for CSV files opened in Excel. The mark
+    /// is written once by the initialization, before the header
+    pub fn set_write_bom(&mut self, write_bom: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.write_bom.is_none() {
+            self.descriptor.write_bom = Some(write_bom);
+        }
+        self
+    }
+
+    /// Returns whether the created file starts with the byte order mark
+    pub fn write_bom(&self) -> bool {
+        self.descriptor.write_bom.unwrap_or(false)
+    }
+
+    /// Sets the treatment of an existing file, overriding the overwrite type
+    /// of the project (e.g. Panic to protect a critical file in a project
+    /// overwriting the others)
+    pub fn set_overwrite_type(
+        &mut self,
+        overwrite_type: OverwriteType,
+    ) -> &mut Self {
+        if !self.initialized() && self.descriptor.overwrite_type.is_none() {
+            self.descriptor.overwrite_type = Some(overwrite_type);
+        }
+        self
+    }
+
+    /// Returns the overwrite type of the file if it overrides the project
+    pub fn overwrite_type(&self) -> Option<OverwriteType> {
+        self.descriptor.overwrite_type
+    }
+
+    /// Sets the filesystem of the output file(s) (std::fs by default, or the
+    /// one of the project initializing the file), e.g. to simulate failures
+    /// of the rotation or the import in tests
+    pub fn set_file_system(
+        &mut self,
+        file_system: impl FileSystem + 'static,
+    ) -> &mut Self {
+        self.file_system = Some(SharedFileSystem(Arc::new(file_system)));
+        self
+    }
+
+    /// Returns the filesystem of the output file(s)
+    pub fn file_system(&self) -> &dyn FileSystem {
+        match &self.file_system {
+            Some(file_system) => file_system.0.as_ref(),
+            None => &RealFileSystem,
+        }
+    }
+
+    /// Helper method returning a handle to the filesystem that does not
+    /// borrow the manager
+    fn shared_file_system(&self) -> Arc<dyn FileSystem> {
+        match &self.file_system {
+            Some(file_system) => Arc::clone(&file_system.0),
+            None => Arc::new(RealFileSystem),
+        }
+    }
+
+    /// Sets the destination of the data, the standard streams need no path
+    /// and are not initialized
+    pub fn set_target(&mut self, target: Target) -> &mut Self {
+        if !self.initialized() && self.descriptor.target.is_none() {
+            self.descriptor.target = Some(target);
+        }
+        self
+    }
+
+    /// Returns whether the data is written to a standard stream instead of
+    /// a file
+    pub fn is_stream(&self) -> bool {
+        matches!(
+            self.descriptor.target,
+            Some(Target::Stdout | Target::Stderr)
+        )
+    }
+
+    /// Sets the compression of the output file, the extension of the codec
+    /// is appended to the path (file.dat -> file.dat.gz)
+    pub fn set_compression(&mut self, compression: Compression) -> &mut Self {
+        if !self.initialized() && self.descriptor.compression.is_none() {
+            self.descriptor.compression = Some(compression);
+        }
+        self
+    }
+
+    /// Enables or disables the canonicalization of the path, when disabled
+    /// the path is kept exactly as constructed from its components
+    pub fn set_canonicalize(&mut self, canonicalize: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.canonicalize.is_none() {
+            self.descriptor.canonicalize = Some(canonicalize);
+        }
+        self
+    }
+
+    /// Returns whether the path is canonicalized
+    pub fn canonicalize(&self) -> bool {
+        self.descriptor.canonicalize.unwrap_or(true)
+    }
+
+    /// Enables or disables the replacement of characters and names that
+    /// are reserved on Windows
+    pub fn set_sanitize_names(&mut self, sanitize_names: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.sanitize_names.is_none() {
+            self.descriptor.sanitize_names = Some(sanitize_names);
+        }
+        self
+    }
+
+    /// Makes try_build fail instead of sanitizing the file name
+    pub fn set_strict_names(&mut self, strict_names: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.strict_names.is_none() {
+            self.descriptor.strict_names = Some(strict_names);
+        }
+        self
+    }
+
+    /// Makes try_build fail if the project directory does not exist (instead
+    /// of creating it on initialization), build ignores this option
+    pub fn set_strict_project_path(
+        &mut self,
+        strict_project_path: bool,
+    ) -> &mut Self {
+        if !self.initialized() && self.descriptor.strict_project_path.is_none()
+        {
+            self.descriptor.strict_project_path = Some(strict_project_path);
+        }
+        self
+    }
+
+    /// Makes the initialization fail if one of the existing files is locked
+    /// by another process (requires the locking feature). The locks are only
+    /// probed before truncating the files, not held afterwards, see
+    /// lock_exclusive to keep a file locked while writing
+    pub fn set_lock_on_init(&mut self, lock_on_init: bool) -> &mut Self {
+        if !self.initialized() && self.descriptor.lock_on_init.is_none() {
+            self.descriptor.lock_on_init = Some(lock_on_init);
+        }
+        self
+    }
+
+    /// Returns whether the locks of the existing files are probed on
+    /// initialization
+    pub fn lock_on_init(&self) -> bool {
+        self.descriptor.lock_on_init.unwrap_or(false)
+    }
+
+    /// Returns whether the file names are sanitized
+    pub fn sanitize_names(&self) -> bool {
+        self.descriptor.sanitize_names.unwrap_or(cfg!(windows))
+    }
+
+    /// Attempts to set the path to the file
+    fn set_path(&mut self) -> &mut Self {
+        self.path = self.calculate_path();
+        self
+    }
+
+    /// Attempts to calculate the path to the file
+    fn calculate_path(&mut self) -> Option<PathBuf> {
+        // To initialize the path, self.output_path, self.name, and
+        // self.extension must be set, as well as self.project_path for a
+        // relative output path
+        match (
+            &self.descriptor.output_path,
+            &self.descriptor.name,
+            &self.descriptor.extension,
+        ) {
+            (Some(output_path), Some(name), Some(extension)) => {
+                let output = Path::new(output_path);
+
+                // An absolute output path ignores the project path
+                let mut path: PathBuf = if output.is_absolute() {
+                    output
+                        .components()
+                        .take_while(|component| {
+                            matches!(
+                                component,
+                                Component::Prefix(_) | Component::RootDir
+                            )
+                        })
+                        .collect()
+                } else {
+                    PathBuf::from(self.descriptor.project_path.as_ref()?)
+                };
+                let relative = match output.strip_prefix(&path) {
+                    Ok(relative) if output.is_absolute() => {
+                        relative.to_string_lossy()
+                    }
+                    _ => output.to_string_lossy(),
+                };
+
+                // Accept both separators for multi-segment output paths
+                for segment in relative.split(['/', '\\']) {
+                    if !segment.is_empty() {
+                        path.push(segment);
+                    }
+                }
+
+                // If dealing with file series, add the index of the file
+                let file_name = match &self.descriptor.series {
+                    Some(series) => {
+                        &format!("{name}_{}", series.number(series.index))
+                    }
+                    None => name,
+                };
+
+                if self.sanitize_names() {
+                    path.push(sanitize_file_name(file_name));
+                    path.set_extension(sanitize_name_component(extension));
+                } else {
+                    path.push(file_name);
+                    path.set_extension(extension);
+                }
+
+                if let Some(compression) = &self.descriptor.compression {
+                    path.as_mut_os_string()
+                        .push(format!(".{}", compression.extension()));
+                }
+
+                if !self.canonicalize() {
+                    return Some(normalize_path(&path));
+                }
+
+                // Attempt to canonicalize the path
+                match path.canonicalize() {
+                    Ok(absolute_path) => Some(absolute_path),
+                    Err(_) => Some(path),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Call to build the path
+    pub fn build(&mut self) -> Self {
+        self.set_path().clone()
+    }
+
+    /// Verifies that the project directory exists in strict mode, the error
+    /// tells whether the directory would be created on initialization (its
+    /// parent exists) or the whole root is missing
+    fn check_project_path(&self) -> Result<(), Error> {
+        let project_path = match (
+            &self.descriptor.strict_project_path,
+            &self.descriptor.project_path,
+        ) {
+            (Some(true), Some(project_path)) => Path::new(project_path),
+            _ => return Ok(()),
+        };
+
+        let file_system = self.file_system();
+        let is_dir = |path: &Path| {
+            file_system.metadata(path).is_ok_and(|meta| meta.is_dir())
+        };
+
+        let reason = if is_dir(project_path) {
+            return Ok(());
+        } else if file_system.exists(project_path) {
+            String::from("project path is not a directory")
+        } else {
+            match project_path.parent() {
+                Some(parent)
+                    if parent.as_os_str().is_empty() || is_dir(parent) =>
+                {
+                    String::from(
+                        "project directory does not exist (it would be \
+                        created on initialization)",
+                    )
+                }
+                Some(parent) => format!(
+                    "project directory does not exist, neither does its \
+                    parent {parent:?}"
+                ),
+                None => String::from("project directory does not exist"),
+            }
+        };
+
+        Err(Error::InvalidPath {
+            path: project_path.to_path_buf(),
+            reason,
+        })
+    }
+
+    /// Verifies that every file in series has its own header (if distinct
+    /// headers are set)
+    fn check_series_headers(&self) -> Result<(), Error> {
+        let headers = match &self.descriptor.series_headers {
+            Some(headers) => headers,
+            None => return Ok(()),
+        };
+
+        let name = self.descriptor.name.clone().unwrap_or_default();
+
+        match &self.descriptor.series {
+            None => Err(Error::InvalidSeries {
+                name,
+                reason: String::from("series headers set for a single file"),
+            }),
+            Some(series) if headers.len() < series.count as usize => {
+                Err(Error::InvalidSeries {
+                    name,
+                    reason: format!(
+                        "{} header(s) provided for {} files",
+                        headers.len(),
+                        series.count
+                    ),
+                })
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Call to build the path, fails if the file name is reserved on
+    /// Windows, or would be changed by the sanitization in strict mode
+    pub fn try_build(&mut self) -> Result<Self, Error> {
+        self.check_series_headers()?;
+        self.check_project_path()?;
+
+        if let (true, Some(name)) =
+            (self.sanitize_names(), &self.descriptor.name)
+        {
+            let names: Vec<String> = match &self.descriptor.series {
+                Some(series) => (0..series.count as usize)
+                    .map(|i| format!("{name}_{}", series.number(i)))
+                    .collect(),
+                None => vec![name.clone()],
+            };
+
+            for name in names {
+                if is_reserved_name(&name) {
+                    return Err(Error::InvalidName {
+                        name,
+                        reason: String::from("reserved device name"),
+                    });
+                }
+
+                if self.descriptor.strict_names.unwrap_or(false)
+                    && sanitize_file_name(&name) != name
+                {
+                    return Err(Error::InvalidName {
+                        name,
+                        reason: String::from(
+                            "contains characters reserved on Windows",
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(self.build())
+    }
+
+    // Modifiers
+    // Note: the setters only work when self.initialized() = true.
+    // Otherwise, one must use Setters
+
+    /// Changeis the path to the root project directory
+    pub fn change_project_path(&mut self, project_path: &str) {
+        if self.initialized() {
+            self.descriptor.project_path = Some(project_path.to_string());
+            self.set_path();
+        }
+    }
+
+    /// Changes the output directory path (relative to the project directory)
+    pub fn change_output_path(&mut self, output_path: &str) {
+        if self.initialized() {
+            self.descriptor.output_path = Some(output_path.to_string());
+            self.set_path();
+        }
+    }
+
+    /// Changes the name of the output file
+    pub fn change_file_name(&mut self, name: &str) {
+        if self.initialized() {
+            self.descriptor.name = Some(name.to_string());
+            self.set_path();
+        }
+    }
+
+    /// Change the extension of the ouput file - use cautiously!
+    pub fn change_extension(&mut self, extension: &str) {
+        if self.initialized() {
+            self.descriptor.extension =
+                Some(without_leading_dot(extension).to_string());
+            self.set_path();
+        }
+    }
+
+    /// Changes the file index if dealing with file series
+    pub fn change_file_index(&mut self, index: usize) {
+        if self.initialized() {
+            if let Some(series) = &mut self.descriptor.series {
+                series.index = index;
+                self.set_path();
+            }
+        }
+    }
+
+    /// Returns an independent built manager of the file with the index in
+    /// series, e.g. for writing the files of a series in parallel. The clone
+    /// is writable if the manager is, the files are still removed only by
+    /// the original manager (if cleanup on drop is enabled)
+    pub fn clone_for_index(&self, index: usize) -> FileManager {
+        let series = match self.descriptor.series {
+            Some(series) if index < series.count as usize => series,
+            Some(series) => panic!(
+                "Index {index} is out of range for a series of {} files",
+                series.count
+            ),
+            None => panic!("Cannot select index {index} of a single file"),
+        };
+
+        let mut file = self.clone();
+        file.descriptor.series = Some(Series { index, ..series });
+        file.set_path();
+        file
+    }
+
+    /// Changes the header, rewriting the leading lines of the existing file(s)
+    pub fn change_header(&mut self, header: &str) -> io::Result<()> {
+        if self.descriptor.compression.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Cannot change the header of a compressed file",
+            ));
+        }
+
+        if self.initialized() {
+            for (index, path) in self.member_paths().iter().enumerate() {
+                if self.file_system().exists(path) {
+                    Self::rewrite_header(
+                        self.file_system(),
+                        path,
+                        &self.leading_lines(self.header_at(index)),
+                        &self.leading_lines(Some(header)),
+                        self.line_ending(),
+                        self.write_bom(),
+                    )?;
+                }
+            }
+            self.descriptor.header = Some(header.to_string());
+            self.descriptor.series_headers = None;
+        }
+        Ok(())
+    }
+
+    /// Helper method replacing the leading lines of an existing file (or
+    /// inserting them if the file does not start with the old lines), the
+    /// byte order mark is kept in front of the new lines
+    fn rewrite_header(
+        file_system: &dyn FileSystem,
+        path: &Path,
+        old_lines: &[String],
+        new_lines: &[String],
+        line_ending: LineEnding,
+        write_bom: bool,
+    ) -> io::Result<()> {
+        let mut reader = io::BufReader::new(
+            file_system.open(path, OpenOptions::new().read(true))?,
+        );
+        if io::BufRead::fill_buf(&mut reader)?.starts_with(&UTF8_BOM) {
+            io::BufRead::consume(&mut reader, UTF8_BOM.len());
+        }
+
+        // Stream the contents to a temporary file next to the original
+        let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let mut writer = io::BufWriter::new(file_system.open(
+            &temp_path,
+            OpenOptions::new().create(true).truncate(true).write(true),
+        )?);
+        if write_bom {
+            writer.write_all(&UTF8_BOM)?;
+        }
+        for line in new_lines {
+            write!(writer, "{line}{}", line_ending.as_str())?;
+        }
+
+        // Skip the previous leading lines
+        let mut first_lines = String::new();
+        let mut matches = true;
+        for old_line in old_lines {
+            let mut line = String::new();
+            io::BufRead::read_line(&mut reader, &mut line)?;
+            matches &= line.trim_end_matches(['\n', '\r']) == old_line;
+            first_lines.push_str(&line);
+        }
+
+        if !matches {
+            writer.write_all(first_lines.as_bytes())?;
+        }
+
+        io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        file_system.set_permissions(
+            &temp_path,
+            file_system.metadata(path)?.permissions(),
+        )?;
+        file_system.rename(&temp_path, path)
+    }
+
+    /// Changes write permissions
+    pub fn change_write_permission(&mut self, writable: bool) {
+        self.writable = writable;
+    }
+
+    /// Changes whether the file(s) are removed when the manager is dropped
+    /// (disabled by default and in the clones of the manager)
+    pub fn change_cleanup_on_drop(&mut self, cleanup_on_drop: bool) {
+        self.cleanup_on_drop = CleanupOnDrop(cleanup_on_drop);
+    }
+
+    /// Returns the header of the output file
+    pub fn header(&self) -> Option<&str> {
+        self.descriptor.header.as_deref()
+    }
+
+    /// Checks whether the manager handles a series of files (see
+    /// series_count and series_index) rather than a single file
+    pub fn is_series(&self) -> bool {
+        self.descriptor.series.is_some()
+    }
+
+    /// Returns the number of files in series (None for a single file)
+    pub fn series_count(&self) -> Option<u32> {
+        self.descriptor.series.map(|series| series.count)
+    }
+
+    /// Returns the index of the current file in series (None for a single
+    /// file)
+    pub fn series_index(&self) -> Option<usize> {
+        self.descriptor.series.map(|series| series.index)
+    }
+
+    /// Returns the lines written at the top of a file with the header: the
+    /// preamble followed by the header, prefixed with the comment character
+    fn leading_lines(&self, header: Option<&str>) -> Vec<String> {
+        let preamble = self
+            .descriptor
+            .preamble
+            .iter()
+            .flatten()
+            .flat_map(|s| s.lines())
+            .map(String::from);
+        let prefix = self
+            .descriptor
+            .comment_char
+            .map(|comment_char| format!("{comment_char} "))
+            .unwrap_or_default();
+        let header = header.into_iter().flat_map(str::lines).map(|line| {
+            match &self.descriptor.columns {
+                Some(columns) => {
+                    columns.format_header(line, prefix.chars().count())
+                }
+                None => line.to_string(),
+            }
+        });
+
+        preamble
+            .chain(header)
+            .map(|line| format!("{prefix}{line}"))
+            .collect()
+    }
+
+    /// Returns the number of lines preceding the data in the current file
+    /// (preamble and header lines). A header with embedded newlines spans
+    /// several lines, e.g. to skip them with numpy.loadtxt(skiprows=...)
+    pub fn header_rows(&self) -> usize {
+        let index = self.descriptor.series.map_or(0, |series| series.index);
+        self.leading_lines(self.header_at(index)).len()
+    }
+
+    /// Returns the header of the file with the index (the distinct header if
+    /// dealing with series)
+    fn header_at(&self, index: usize) -> Option<&str> {
+        match (&self.descriptor.series, &self.descriptor.series_headers) {
+            (Some(_), Some(headers)) => headers.get(index).map(String::as_str),
+            _ => self.descriptor.header.as_deref(),
+        }
+    }
+
+    /// Returns the path to the output file
+    pub fn path(&self) -> &PathBuf {
+        match &self.path {
+            Some(path) => path,
+            None => panic!(
+                "Attempting to access the path of uninitialized FileManager\n\
+                Curently,\nproject_path: {:?},\noutput_path: {:?},\
+                \nname: {:?},\nextension: {:?},\n",
+                self.descriptor.project_path,
+                self.descriptor.output_path,
+                self.descriptor.name,
+                self.descriptor.extension
+            ),
+        }
+    }
+
+    /// Returns the path to the output file with the "." and ".." components
+    /// collapsed, without accessing the filesystem (symlinks are kept)
+    pub fn normalized_path(&self) -> PathBuf {
+        normalize_path(self.path())
+    }
+
+    /// Returns the paths to all output files (all members if dealing with
+    /// series)
+    fn member_paths(&self) -> Vec<PathBuf> {
+        if self.is_stream() {
+            return Vec::new();
+        }
+
+        match &self.descriptor.series {
+            None => vec![self.path().clone()],
+            Some(series) => {
+                let mut file = self.clone();
+
+                (0..series.count as usize)
+                    .map(|i| {
+                        file.change_file_index(i);
+                        file.path().clone()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the path to the output file as a string
+    pub fn path_string(&self) -> String {
+        match self.path().to_str() {
+            Some(path_str) => path_str.to_string(),
+            None => panic!(
+                "Could not convert file path {:?} to string",
+                self.path()
+            ),
+        }
+    }
+
+    // Initializer methods
+
+    /// Creates the output file (or files if dealing with series)
+    pub fn initialize_output(&mut self) {
+        let file_system = self.shared_file_system();
+        self.initialize_output_in(file_system.as_ref())
+    }
+
+    /// Creates the output file(s) as initialize_output, through the
+    /// filesystem (e.g. one simulating failures in tests)
+    pub fn initialize_output_in(&mut self, file_system: &dyn FileSystem) {
+        self.initialize_output_with(
+            &mut |_| (),
+            &RetryPolicy::default(),
+            file_system,
+            false,
+        )
+    }
+
+    /// Helper method creating the output file(s), the callback is called
+    /// with the path of every created file. Existing files with the same
+    /// initial contents are kept (and not reported) if skip_unchanged is set
+    fn initialize_output_with(
+        &mut self,
+        on_created: &mut dyn FnMut(&Path),
+        retry: &RetryPolicy,
+        file_system: &dyn FileSystem,
+        skip_unchanged: bool,
+    ) {
+        // Nothing to create for the standard streams
+        if self.is_stream() {
+            self.writable = true;
+            return;
+        }
+
+        // Make sure that the path is initialized
+        if !self.initialized() {
+            self.set_path();
+        }
+
+        // Create output directory
+        let created_dirs = match self.path().parent() {
+            None => panic!(
+                "No parent directory found for {:?} FileManager",
+                self.path()
+            ),
+            Some(path) => {
+                match retry.run(|| self.create_output_dir(path, file_system)) {
+                    Ok(created_dirs) => created_dirs,
+                    Err(reason) => panic!(
+                        "Cannot initialize output directory {:?}: {:?}",
+                        path, reason,
+                    ),
+                }
+            }
+        };
+        self.created_dirs.extend(created_dirs);
+
+        // Refuse to truncate files locked by a competing process
+        #[cfg(feature = "locking")]
+        if self.lock_on_init() {
+            for path in self.member_paths() {
+                if !file_system.exists(&path) {
+                    continue;
+                }
+                if let Err(reason) = Self::try_lock_path(&path) {
+                    panic!(
+                        "Could not lock file {:?}, it may be used by another \
+                        process: {:?}",
+                        path, reason
+                    );
+                }
+            }
+        }
+
+        // Initialize file(s)
+        match &self.descriptor.series {
+            None => {
+                if self.initialize_file(retry, file_system, skip_unchanged) {
+                    on_created(self.path());
+                }
+            }
+            Some(series) => {
+                for i in 0..series.count as usize {
+                    self.change_file_index(i);
+                    self.set_path();
+
+                    if self.initialize_file(retry, file_system, skip_unchanged)
+                    {
+                        on_created(self.path());
+                    }
+                }
+            }
+        }
+
+        // Change the writing permissions
+        self.writable = true;
+
+        if !self.canonicalize() {
+            return;
+        }
+
+        // Attempt to canonicalize the path
+        match self.path().canonicalize() {
+            Ok(absolute_path) => self.path = Some(absolute_path),
+            Err(reason) => {
+                println!(
+                    "Could not canonicalize path {:?}, using relative \
+                        form: {:?}",
+                    self.path(),
+                    reason.kind()
+                );
+            }
+        }
+    }
+
+    /// Creates the file (and its directories) only if it does not exist yet,
+    /// keeping the contents of an existing file, and allows writing to it. A
+    /// new file gets the header (for series only the current file is created)
+    pub fn touch(&mut self) -> io::Result<()> {
+        if !self.is_stream() {
+            if !self.initialized() {
+                self.set_path();
+            }
+
+            let file_system = self.shared_file_system();
+            if let Some(parent) = self.path().parent() {
+                let created_dirs =
+                    self.create_output_dir(parent, file_system.as_ref())?;
+                self.created_dirs.extend(created_dirs);
+            }
+
+            if !file_system.exists(self.path()) {
+                self.create_file(file_system.as_ref())?;
+            }
+        }
+
+        self.writable = true;
+        Ok(())
+    }
+
+    /// Copies an external file to the path of the file (creating its
+    /// directories), replacing an existing file, and allows writing to it.
+    /// Unlike the initialization the contents are kept as they are, no header
+    /// is written (or compression applied)
+    pub fn import_from(&mut self, source: &Path) -> io::Result<()> {
+        if self.is_stream() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot import a file into a standard stream",
+            ));
+        }
+
+        if !self.initialized() {
+            self.set_path();
+        }
+
+        let file_system = self.shared_file_system();
+        if let Some(parent) = self.path().parent() {
+            let created_dirs =
+                self.create_output_dir(parent, file_system.as_ref())?;
+            self.created_dirs.extend(created_dirs);
+        }
+
+        file_system.copy(source, self.path())?;
+
+        self.writable = true;
+        Ok(())
+    }
+
+    /// Helper method for creating the output directory tree, returns the
+    /// directories that did not exist before (outermost first)
+    fn create_output_dir(
+        &self,
+        path: &Path,
+        file_system: &dyn FileSystem,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut missing: Vec<PathBuf> = path
+            .ancestors()
+            .take_while(|dir| {
+                !dir.as_os_str().is_empty() && !file_system.exists(dir)
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        if missing.is_empty() {
+            return Ok(missing);
+        }
+        missing.reverse();
+
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+
+        // Apply the directory permissions (ignored on non-Unix platforms)
+        #[cfg(unix)]
+        if let Some(dir_mode) = self.descriptor.dir_mode {
+            builder.mode(dir_mode);
+        }
+
+        file_system.create_dir_all(path, &builder)?;
+        Ok(missing)
+    }
+
+    /// Returns the directories created while initializing the file(s)
+    /// (outermost first), directories that existed before are not included
+    pub fn created_dirs(&self) -> &[PathBuf] {
+        &self.created_dirs
+    }
+
+    /// Removes the directories created while initializing the file(s),
+    /// innermost first. Fails if a directory is not empty (e.g. the output
+    /// files were not removed), pre-existing directories are never touched
+    pub fn remove_created_dirs(&mut self) -> io::Result<()> {
+        let file_system = self.shared_file_system();
+        while let Some(dir) = self.created_dirs.last() {
+            file_system.remove_dir(dir)?;
+            self.created_dirs.pop();
+        }
+        Ok(())
+    }
+
+    /// Helper method for initializing a single (new) file, returns whether
+    /// the file was written (an unchanged file is kept if skip_unchanged)
+    fn initialize_file(
+        &self,
+        retry: &RetryPolicy,
+        file_system: &dyn FileSystem,
+        skip_unchanged: bool,
+    ) -> bool {
+        if skip_unchanged && self.is_unchanged() {
+            return false;
+        }
+
+        if let Err(reason) = retry.run(|| self.create_file(file_system)) {
+            panic!("Could not initialize file {:?}: {:?}", self.path(), reason)
+        }
+        true
+    }
+
+    /// Checks whether the existing file at the current path holds exactly
+    /// the initial contents (decompressed if dealing with compression)
+    fn is_unchanged(&self) -> bool {
+        let expected = self.initial_contents();
+
+        let mut contents = Vec::new();
+        let read = compression::open_reader(self.path()).and_then(|reader| {
+            // One extra byte is enough to tell that the file holds more
+            reader
+                .take(expected.len() as u64 + 1)
+                .read_to_end(&mut contents)
+        });
+
+        read.is_ok() && contents == expected
+    }
+
+    /// Returns the contents written to a new file: the byte order mark, the
+    /// preamble and the header of the current file
+    fn initial_contents(&self) -> Vec<u8> {
+        let mut contents = Vec::new();
+        if self.write_bom() {
+            contents.extend_from_slice(&UTF8_BOM);
+        }
+
+        let index = self.descriptor.series.map_or(0, |series| series.index);
+        for line in self.leading_lines(self.header_at(index)) {
+            contents.extend_from_slice(line.as_bytes());
+            contents.extend_from_slice(self.line_ending().as_str().as_bytes());
+        }
+        contents
+    }
+
+    /// Creates (or truncates) the file at the current path and writes the
+    /// header
+    fn create_file(&self, file_system: &dyn FileSystem) -> io::Result<()> {
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+
+        // Apply the file permissions (ignored on non-Unix platforms)
+        #[cfg(unix)]
+        if let Some(mode) = self.descriptor.mode {
+            options.mode(mode);
+        }
+
+        let file = file_system.open(self.path(), &options)?;
+
+        // Write the byte order mark, the preamble and the header
+        let contents = self.initial_contents();
+        if !contents.is_empty() {
+            let mut writer = self.encode(file)?;
+            writer.write_all(&contents)?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    // Write methods
+
+    /// Returns write permission of the file manager
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    /// Checks the write permission once and returns the handle carrying the
+    /// write methods, the permission can't change while the handle is alive
+    pub fn try_writer(&self) -> Result<WritableHandle<'_>, Error> {
+        if !self.writable {
+            return Err(Error::NotWritable {
+                path: self.path.clone().unwrap_or_default(),
+            });
+        }
+        Ok(WritableHandle { file: self })
+    }
+
+    /// Opens a file to append the data, a compressed file cannot be opened
+    /// as a raw file (use open_writer or open_buffer)
+    pub fn open_file(&self) -> fs::File {
+        match self.try_open_file() {
+            Ok(file) => file,
+            Err(reason) => {
+                panic!("Could not open file {:?}: {}", self.path(), reason)
+            }
+        }
+    }
+
+    /// Attempts to open a file to append the data, refusing compressed files
+    /// whose streams would be corrupted by raw writes
+    fn try_open_file(&self) -> io::Result<fs::File> {
+        if self.descriptor.compression.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Cannot open a compressed file as a raw file, use open_writer",
+            ));
+        }
+
+        self.open_appending()
+    }
+
+    /// Helper method opening the file to append the data, rotating it first
+    /// if it exceeds the maximum size
+    fn open_appending(&self) -> io::Result<fs::File> {
+        if self.is_stream() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Cannot open a standard stream as a file, use open_writer",
+            ));
+        }
+
+        if !self.writable() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                Error::NotWritable {
+                    path: self.path.clone().unwrap_or_default(),
+                }
+                .to_string(),
+            ));
+        }
+
+        self.rotate_if_full()?;
+
+        self.file_system()
+            .open(self.path(), OpenOptions::new().append(true))
+    }
+
+    /// Appends a line to the file. Every call on a compressed file appends a
+    /// complete compressed stream (a gzip member, a zstd frame or a bzip2
+    /// stream), the result is a valid multi-member file read back in full by
+    /// open_reader and by the usual tools (e.g. gunzip, zcat)
+    pub fn append_line(&self, line: &str) -> io::Result<()> {
+        self.append_lines([line])
+    }
+
+    /// Appends a line of values padded (or truncated) to the fixed-width
+    /// columns set by set_columns, fails if no columns are set, if the
+    /// number of values does not match or if a value is too wide for a
+    /// strict layout
+    pub fn write_columns(&self, values: &[&str]) -> io::Result<()> {
+        let columns = self.descriptor.columns.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No columns are set for file {:?}", self.path),
+            )
+        })?;
+
+        let line = columns.format(values).map_err(|reason| {
+            io::Error::new(io::ErrorKind::InvalidInput, reason)
+        })?;
+        self.append_line(&line)
+    }
+
+    /// Appends a value serialized as a single JSON line (JSONL)
+    pub fn append_json<T: Serialize>(&self, value: &T) -> io::Result<()> {
+        self.append_line(&serde_json::to_string(value)?)
+    }
+
+    /// Appends the values as JSON lines through a single writer
+    pub fn append_json_batch<'a, T: Serialize + 'a>(
+        &self,
+        values: impl IntoIterator<Item = &'a T>,
+    ) -> io::Result<()> {
+        let lines = values
+            .into_iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.append_lines(lines)
+    }
+
+    /// Helper method appending the lines through a single writer
+    fn append_lines(
+        &self,
+        lines: impl IntoIterator<Item = impl fmt::Display>,
+    ) -> io::Result<()> {
+        let mut writer = self.open_writer()?;
+        for line in lines {
+            write!(writer, "{line}{}", self.line_ending().as_str())?;
+        }
+        writer.flush()?;
+
+        if self.descriptor.sync_on_append.unwrap_or(false) {
+            // Finish the compressed stream before syncing
+            drop(writer);
+            self.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Streams the lines of the file through the function into the
+    /// destination (appended to it, e.g. after its header), the lines for
+    /// which the function returns None are dropped. The destination may be
+    /// the file itself, which is then replaced by the transformed lines
+    /// (every line goes through the function, the header included)
+    pub fn transform_lines<F: FnMut(String) -> Option<String>>(
+        &self,
+        dest: &FileManager,
+        mut f: F,
+    ) -> io::Result<()> {
+        let not_built = |file: &FileManager| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                Error::InvalidPath {
+                    path: file.path.clone().unwrap_or_default(),
+                    reason: String::from("the file manager is not built"),
+                }
+                .to_string(),
+            )
+        };
+        let source = self.path.as_ref().ok_or_else(|| not_built(self))?;
+        let in_place = match (&dest.path, dest.is_stream()) {
+            (_, true) => false,
+            (Some(dest_path), false) => same_file(source, dest_path),
+            (None, false) => return Err(not_built(dest)),
+        };
+
+        let reader = self.open_reader()?;
+
+        let mut first = true;
+        let write_lines = move |writer: &mut dyn Write| -> io::Result<()> {
+            for line in io::BufRead::lines(reader) {
+                let mut line = line?;
+                // The byte order mark is not a part of the first line
+                if first {
+                    if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                        line = stripped.to_string();
+                    }
+                    first = false;
+                }
+
+                if let Some(line) = f(line) {
+                    write!(writer, "{line}{}", dest.line_ending().as_str())?;
+                }
+            }
+            writer.flush()
+        };
+
+        if !in_place {
+            let mut writer = io::BufWriter::new(dest.open_writer()?);
+            return write_lines(&mut writer);
+        }
+
+        if !self.writable() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                Error::NotWritable {
+                    path: self.path().clone(),
+                }
+                .to_string(),
+            ));
+        }
+
+        // Stream the lines to a temporary file replacing the original
+        let path = self.path();
+        let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        if let Some(mode) = self.descriptor.mode {
+            options.mode(mode);
+        }
+
+        let file_system = self.file_system();
+        {
+            let temp_file = file_system.open(&temp_path, &options)?;
+            let mut writer = self.encode(temp_file)?;
+            if self.write_bom() {
+                writer.write_all(&UTF8_BOM)?;
+            }
+            write_lines(&mut io::BufWriter::new(writer))?;
+        }
+
+        file_system.set_permissions(
+            &temp_path,
+            file_system.metadata(path)?.permissions(),
+        )?;
+        file_system.rename(&temp_path, path)
+    }
+
+    /// Opens a writer appending the data, compressing it if the compression
+    /// is set (each writer appends a new compressed stream, the streams are
+    /// concatenated by the readers)
+    pub fn open_writer(&self) -> io::Result<Box<dyn Write>> {
+        match self.descriptor.target {
+            Some(Target::Stdout) => return Ok(Box::new(io::stdout())),
+            Some(Target::Stderr) => return Ok(Box::new(io::stderr())),
+            _ => (),
+        }
+
+        let file = self.open_appending()?;
+        self.encode(file)
+    }
+
+    /// Helper method wrapping the file in the encoder of the compression
+    fn encode(&self, file: fs::File) -> io::Result<Box<dyn Write>> {
+        match &self.descriptor.compression {
+            Some(compression) => compression.encoder(file),
+            None => Ok(Box::new(file)),
+        }
+    }
+
+    /// Opens a file in a buffer to append the data (for larger arrays), the
+    /// buffer is flushed when dropped and failures are logged
+    pub fn open_buffer(&self) -> BufferedFile {
+        match self.try_open_buffer() {
+            Ok(buffer) => buffer,
+            Err(reason) => {
+                panic!("Could not open file {:?}: {}", self.path, reason)
+            }
+        }
+    }
+
+    /// Opens a file in a buffer of the given capacity (in bytes) to append
+    /// the data, sizing the buffer to the expected output reduces the number
+    /// of writes for large arrays
+    pub fn open_buffer_with_capacity(&self, capacity: usize) -> BufferedFile {
+        match self.try_open_buffer_with_capacity(capacity) {
+            Ok(buffer) => buffer,
+            Err(reason) => {
+                panic!("Could not open file {:?}: {}", self.path, reason)
+            }
+        }
+    }
+
+    /// Attempts to open a buffer appending the data through open_writer (a
+    /// compressed file gets a new stream)
+    fn try_open_buffer(&self) -> io::Result<BufferedFile> {
+        Ok(BufferedFile {
+            writer: io::BufWriter::new(self.open_writer()?),
+            path: self.path.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Attempts to open a buffer of the given capacity appending the data
+    fn try_open_buffer_with_capacity(
+        &self,
+        capacity: usize,
+    ) -> io::Result<BufferedFile> {
+        Ok(BufferedFile {
+            writer: io::BufWriter::with_capacity(capacity, self.open_writer()?),
+            path: self.path.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Flushes the data written to the file to the disk
+    pub fn flush(&self) -> io::Result<()> {
+        self.sync_all()
+    }
+
+    /// Forces the data and metadata of the file to the disk (fsync), so that
+    /// the written data survives a crash (flushes the standard streams)
+    pub fn sync_all(&self) -> io::Result<()> {
+        if self.is_stream() {
+            return self.open_writer()?.flush();
+        }
+
+        self.file_system()
+            .open(self.path(), OpenOptions::new().append(true))?
+            .sync_all()
+    }
+
+    /// Rotates the file if it has reached the maximum size
+    fn rotate_if_full(&self) -> io::Result<()> {
+        let max_size = match self.descriptor.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(()),
+        };
+
+        let file_system = self.file_system();
+        let size = match file_system.metadata(self.path()) {
+            Ok(metadata) => metadata.len(),
+            Err(reason) if reason.kind() == io::ErrorKind::NotFound => {
+                return Ok(())
+            }
+            Err(reason) => return Err(reason),
+        };
+
+        if size < max_size {
+            return Ok(());
+        }
+
+        // Find the next free numbered slot
+        let mut index = 1;
+        while file_system.exists(&numbered_path(self.path(), index)) {
+            index += 1;
+        }
+
+        file_system.rename(self.path(), &numbered_path(self.path(), index))?;
+
+        // Start a fresh file
+        self.create_file(file_system)
+    }
+
+    // Read methods
+
+    /// Opens a file in a buffer to read the data (does not require write
+    /// permissions)
+    pub fn open_read_buffer(&self) -> io::Result<io::BufReader<fs::File>> {
+        Ok(io::BufReader::new(fs::File::open(self.path())?))
+    }
+
+    /// Opens a file in a buffer to read the data, decompressing it if the
+    /// extension matches one of the enabled codecs
+    pub fn open_reader(&self) -> io::Result<Box<dyn io::BufRead>> {
+        compression::open_reader(self.path())
+    }
+
+    /// Opens a CSV writer appending records to the file through open_writer
+    /// (a compressed file gets a new stream), the header is used as the
+    /// header row if nothing has been written to the file yet
+    #[cfg(feature = "csv")]
+    pub fn csv_writer(&self) -> csv::Writer<Box<dyn Write>> {
+        let mut writer = match self.open_writer() {
+            Ok(writer) => writer,
+            Err(reason) => {
+                panic!("Could not open file {:?}: {}", self.path, reason)
+            }
+        };
+
+        if let (Some(header), false) =
+            (&self.descriptor.header, self.is_stream())
+        {
+            let is_empty = match fs::metadata(self.path()) {
+                Ok(metadata) => metadata.len() == 0,
+                Err(reason) => panic!(
+                    "Could not read metadata of file {:?}: {:?}",
+                    self.path(),
+                    reason
+                ),
+            };
+
+            if is_empty {
+                if let Err(reason) =
+                    write!(writer, "{header}{}", self.line_ending().as_str())
+                {
+                    panic!(
+                        "Could not write to file {:?}: {:?}",
+                        self.path(),
+                        reason
+                    );
+                }
+            }
+        }
+
+        let terminator = match self.line_ending() {
+            LineEnding::Lf => csv::Terminator::Any(b'\n'),
+            LineEnding::Crlf => csv::Terminator::CRLF,
+        };
+
+        csv::WriterBuilder::new()
+            .has_headers(false)
+            .delimiter(self.descriptor.delimiter.unwrap_or(b','))
+            .terminator(terminator)
+            .from_writer(writer)
+    }
+
+    /// Appends a structure to the file as a single CSV record
+    #[cfg(feature = "csv")]
+    pub fn write_record<T: Serialize>(&self, record: &T) -> io::Result<()> {
+        let mut writer = self.csv_writer();
+        writer.serialize(record)?;
+        writer.flush()
+    }
+
+    // Locking methods
+
+    /// Blocks until an exclusive lock on the file is acquired, the lock is
+    /// held until the returned guard is dropped
+    #[cfg(feature = "locking")]
+    pub fn lock_exclusive(&self) -> io::Result<FileLock> {
+        let file = OpenOptions::new().append(true).open(self.path())?;
+        file.lock_exclusive()?;
+
+        Ok(FileLock { file })
+    }
+
+    /// Attempts to acquire an exclusive lock on the file without blocking,
+    /// fails if the lock is held by someone else
+    #[cfg(feature = "locking")]
+    pub fn try_lock_exclusive(&self) -> io::Result<FileLock> {
+        Self::try_lock_path(self.path())
+    }
+
+    /// Helper method attempting to lock the file at the path without
+    /// blocking
+    #[cfg(feature = "locking")]
+    fn try_lock_path(path: &Path) -> io::Result<FileLock> {
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.try_lock_exclusive()?;
+
+        Ok(FileLock { file })
+    }
+
+    // Checksum methods
+
+    /// Returns the hex-encoded SHA-256 of the file contents
+    #[cfg(feature = "checksum")]
+    pub fn checksum(&self) -> io::Result<String> {
+        sha256_hex(self.path())
+    }
+
+    /// Compares the checksum of the file with the expected one
+    #[cfg(feature = "checksum")]
+    pub fn verify_checksum(&self, expected: &str) -> io::Result<bool> {
+        Ok(self.checksum()?.eq_ignore_ascii_case(expected))
+    }
+
+    /// Returns the checksums of all files in series, keyed by file index
+    #[cfg(feature = "checksum")]
+    pub fn series_checksums(&self) -> io::Result<BTreeMap<usize, String>> {
+        match &self.descriptor.series {
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("File {:?} is not a file series", self.path()),
+            )),
+            Some(series) => {
+                let mut file = self.clone();
+
+                (0..series.count as usize)
+                    .map(|i| {
+                        file.change_file_index(i);
+                        Ok((i, file.checksum()?))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Computes the hex-encoded SHA-256 of a file
+#[cfg(feature = "checksum")]
+fn sha256_hex(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+
+    // Stream the contents through the hasher
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+impl fmt::Display for FileManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.descriptor.target, &self.path) {
+            (Some(Target::Stdout), _) => write!(f, "<stdout>")?,
+            (Some(Target::Stderr), _) => write!(f, "<stderr>")?,
+            (_, Some(path)) => write!(f, "{}", path.display())?,
+            (_, None) => write!(f, "<unresolved path>")?,
+        }
+
+        let mut details = Vec::new();
+
+        if let Some(series) = &self.descriptor.series {
+            details.push(format!("series of {} files", series.count));
+        }
+
+        match &self.descriptor.header {
+            Some(header) => details.push(format!("header {header:?}")),
+            None => details.push(String::from("no header")),
+        }
+
+        details.push(String::from(if self.writable {
+            "writable"
+        } else {
+            "read-only"
+        }));
+
+        write!(f, " ({})", details.join(", "))
+    }
+}
+
+impl Drop for FileManager {
+    fn drop(&mut self) {
+        if self.cleanup_on_drop.0 && self.initialized() {
+            for path in self.member_paths() {
+                // The file may have been removed already
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Generates a name unique to this process and moment
+pub(crate) fn unique_name(prefix: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.subsec_nanos());
+
+    format!(
+        "{prefix}_{}_{nanos}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Resolves a relative path against the directory of the config file (kept
+/// as is if the config was not loaded from a file)
+fn relative_to_config(path: &str, source_path: Option<&Path>) -> String {
+    let config_dir = match source_path.and_then(Path::parent) {
+        Some(config_dir) if Path::new(path).is_relative() => config_dir,
+        _ => return path.to_string(),
+    };
+
+    match config_dir.join(path).to_str() {
+        Some(resolved) => resolved.to_string(),
+        None => path.to_string(),
+    }
+}
+
+/// Renames the directory, or copies it and removes the original if it cannot
+/// be renamed across filesystems
+fn move_dir(
+    file_system: &dyn FileSystem,
+    from: &Path,
+    to: &Path,
+) -> io::Result<()> {
+    match file_system.rename(from, to) {
+        Err(reason) if reason.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir_all(file_system, from, to)?;
+            file_system.remove_dir_all(from)
+        }
+        result => result,
+    }
+}
+
+/// Copies the directory tree recursively
+fn copy_dir_all(
+    file_system: &dyn FileSystem,
+    from: &Path,
+    to: &Path,
+) -> io::Result<()> {
+    file_system.create_dir_all(to, fs::DirBuilder::new().recursive(true))?;
+
+    for entry in file_system.read_dir(from)? {
+        let entry = entry?;
+        let destination = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(file_system, &entry.path(), &destination)?;
+        } else {
+            file_system.copy(&entry.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Collapses the "." and ".." components of the path without accessing the
+/// filesystem (symlinks are not resolved), leading ".." of a relative path
+/// are kept and ".." of the root is the root itself
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// Checks whether the paths point to the same file, comparing the canonical
+/// paths if both exist and the normalized paths otherwise
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => normalize_path(a) == normalize_path(b),
+    }
+}
+
+/// Strips a single leading dot of an extension (".dat" -> "dat")
+fn without_leading_dot(extension: &str) -> &str {
+    extension.strip_prefix('.').unwrap_or(extension)
+}
+
+/// Inserts a number before the extension of the path (file.dat -> file.1.dat)
+fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let file_name = match path.extension() {
+        Some(extension) => {
+            format!("{stem}.{index}.{}", extension.to_string_lossy())
+        }
+        None => format!("{stem}.{index}"),
+    };
+
+    path.with_file_name(file_name)
+}
+
+/// UTF-8 byte order mark
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// Characters that cannot be used in file names on Windows
+const RESERVED_CHARS: [char; 9] =
+    ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names that cannot be used as file names on Windows
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6",
+    "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks if the name (without extension) is a reserved device name
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Replaces reserved characters and trailing dots/spaces with underscores
+fn sanitize_name_component(name: &str) -> String {
+    let n_kept = name.trim_end_matches(['.', ' ']).chars().count();
+
+    name.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if i >= n_kept || c.is_control() || RESERVED_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Makes a file name (without extension) portable across platforms
+fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized = sanitize_name_component(name);
+
+    if is_reserved_name(&sanitized) {
+        sanitized.push('_');
+    }
+
+    sanitized
+}
+
+/// Buffered handle of an output file, unlike io::BufWriter a failure to
+/// flush the buffer on drop is logged instead of silently ignored
+pub struct BufferedFile {
+    // Buffered writer of the file (compressing the data if required)
+    writer: io::BufWriter<Box<dyn Write>>,
+    // Path of the file for the error messages
+    path: PathBuf,
+}
+
+impl BufferedFile {
+    /// Returns the capacity of the buffer in bytes
+    pub fn capacity(&self) -> usize {
+        self.writer.capacity()
+    }
+}
+
+impl Write for BufferedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl fmt::Debug for BufferedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferedFile")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for BufferedFile {
+    fn drop(&mut self) {
+        if let Err(reason) = self.writer.flush() {
+            log::error!(
+                "Could not flush the buffer of file {:?}: {}",
+                self.path,
+                reason
+            );
+        }
+    }
+}
+
+/// Write access to an output file with a checked write permission, returned
+/// by FileManager::try_writer
+#[derive(Clone, Copy, Debug)]
+pub struct WritableHandle<'a> {
+    // Writable file manager
+    file: &'a FileManager,
+}
+
+impl WritableHandle<'_> {
+    /// Returns the file manager of the handle
+    pub fn file(&self) -> &FileManager {
+        self.file
+    }
+
+    /// Opens the file to append the data
+    pub fn open_file(&self) -> io::Result<fs::File> {
+        self.file.try_open_file()
+    }
+
+    /// Opens a writer appending the data (see FileManager::open_writer)
+    pub fn open_writer(&self) -> io::Result<Box<dyn Write>> {
+        self.file.open_writer()
+    }
+
+    /// Opens the file in a buffer to append the data
+    pub fn open_buffer(&self) -> io::Result<BufferedFile> {
+        self.file.try_open_buffer()
+    }
+
+    /// Opens the file in a buffer of the given capacity to append the data
+    pub fn open_buffer_with_capacity(
+        &self,
+        capacity: usize,
+    ) -> io::Result<BufferedFile> {
+        self.file.try_open_buffer_with_capacity(capacity)
+    }
+
+    /// Appends a line to the file
+    pub fn append_line(&self, line: &str) -> io::Result<()> {
+        self.file.append_line(line)
+    }
+
+    /// Forces the written data to the disk
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// Guard for an advisory lock on an output file
+#[cfg(feature = "locking")]
+#[derive(Debug)]
+pub struct FileLock {
+    // Handle the lock is attached to
+    file: fs::File,
+}
+
+#[cfg(feature = "locking")]
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // The lock is released by the OS when the handle is closed anyway
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::remove_dir_all;
+
+    use super::*;
+
+    use crate::config_parse::{from_config_or_default, from_config_root};
+
+    use crate::testing::TempProject;
+
+    #[test]
+    fn overwrite_files() {
+        // Setup test project directory tree
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let project_manager = project.project();
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("dir_1")
+                .set_file_name("file_1")
+                .set_extension("dat")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir_1")
+                .set_file_name("file_2")
+                .set_extension("csv")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir_2")
+                .set_file_name("file_3")
+                .set_extension("txt")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir_3")
+                .set_file_name("file_4")
+                .build(),
+        ];
+
+        // The same managers can be initialized repeatedly
+        for _ in 0..2 {
+            if let Err(reason) =
+                project_manager.initialize_output_files(&mut test_files)
+            {
+                panic!(
+                    "Could not initialize output files for Overwrite test: \
+                    {reason}"
+                )
+            }
+        }
+
+        let [test_file_1, test_file_2, test_file_3, test_file_4] = &test_files;
+
+        // Verify that correct files were initialized
+        assert!(
+            project.path().join("dir_1/file_1.dat").exists(),
+            "file_1.dat was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_1/file_2.csv").exists(),
+            "file_2.csv was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_2/file_3.txt").exists(),
+            "file_3.txt was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_3/file_4.dat").exists(),
+            "file_4.dat was not created!"
+        );
+
+        // Verify final states of the FileManager
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_1.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_1.path(),
+            "The path of file_1.dat does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_2.csv")
+                .canonicalize()
+                .unwrap(),
+            *test_file_2.path(),
+            "The path of file_2.csv does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_2/file_3.txt")
+                .canonicalize()
+                .unwrap(),
+            *test_file_3.path(),
+            "The path of file_3.txt does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_3/file_4.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_4.path(),
+            "The path of file_4.dat does not match the expected one."
+        );
+    }
+
+    #[test]
+    fn forbidden_overwrite() {
+        // Setup test project directory tree
+        let project = TempProject::new("dat", OverwriteType::Panic).unwrap();
+        let project_manager = project.project();
+
+        let mut test_file_1 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .set_extension("dat")
+            .build();
+
+        let mut test_file_2 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_2")
+            .set_extension("csv")
+            .build();
+
+        let mut test_file_3 = FileManager::default()
+            .set_output_path("dir_2")
+            .set_file_name("file_3")
+            .set_extension("dat")
+            .build();
+
+        let mut test_file_4 = FileManager::default()
+            .set_output_path("dir_3")
+            .set_file_name("file_4")
+            .set_extension("txt")
+            .build();
+
+        let files = vec![
+            &mut test_file_1,
+            &mut test_file_2,
+            &mut test_file_3,
+            &mut test_file_4,
+        ];
+
+        if let Err(reason) = project_manager.initialize_output_files(files) {
+            panic!(
+                "Could not initialize output files for Panic test: \
+                {reason}"
+            )
+        }
+
+        let mut test_file_1_copy = FileManager::default()
+            .set_header("New file_1")
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .set_extension("dat")
+            .build();
+
+        let files = vec![&mut test_file_1_copy];
+
+        assert_eq!(
+            Err(String::from(
+                "Permission denied to overwrite existing output files."
+            )),
+            project_manager.initialize_output_files(files)
+        );
+    }
+
+    #[test]
+    fn archive_files() {
+        // Setup test project directory tree
+        let project = TempProject::new("dat", OverwriteType::Archive).unwrap();
+        let project_manager = project.project();
+
+        let mut test_file_1 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .set_extension("dat")
+            .build();
+
+        let mut test_file_2 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_2")
+            .set_extension("csv")
+            .build();
+
+        let mut test_file_3 = FileManager::default()
+            .set_output_path("dir_2")
+            .set_file_name("file_3")
+            .set_extension("txt")
+            .build();
+
+        let mut test_file_4 = FileManager::default()
+            .set_output_path("dir_3")
+            .set_file_name("file_4")
+            .build();
+
+        let files = vec![
+            &mut test_file_1,
+            &mut test_file_2,
+            &mut test_file_3,
+            &mut test_file_4,
+        ];
+
+        if let Err(reason) = project_manager.initialize_output_files(files) {
+            panic!(
+                "Could not initialize output files for Archive test: \
+                {reason}"
+            )
+        }
+
+        let mut test_file_1_copy = FileManager::default()
+            .set_header("New file_1")
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .build();
+
+        let files = vec![&mut test_file_1_copy];
+
+        if let Err(reason) = project_manager.initialize_output_files(files) {
+            panic!(
+                "Could not initialize output files for Archive test: \
+                {reason}"
+            )
+        }
+
+        // Verify that correct files were initialized
+        assert!(
+            project.path().join("dir_1/file_1.dat").exists(),
+            "file_1.dat was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_1/file_2.csv").exists(),
+            "file_2.csv was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_2/file_3.txt").exists(),
+            "file_3.txt was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_3/file_4.dat").exists(),
+            "file_4.dat was not created!"
+        );
+
+        assert!(
+            project.path().join("archive/dir_1/file_1.dat").exists(),
+            "file_1.dat was not created!"
+        );
+
+        // Verify final states of the FileManager
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_1.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_1.path(),
+            "The path of file_1.dat does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_2.csv")
+                .canonicalize()
+                .unwrap(),
+            *test_file_2.path(),
+            "The path of file_2.csv does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_2/file_3.txt")
+                .canonicalize()
+                .unwrap(),
+            *test_file_3.path(),
+            "The path of file_3.txt does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_3/file_4.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_4.path(),
+            "The path of file_4.dat does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_1.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_1_copy.path(),
+            "The path of file_1.dat does not match the expected one."
+        );
+    }
+
+    #[test]
+    fn archive_single_file() {
+        let mut project =
+            TempProject::new("dat", OverwriteType::Archive).unwrap();
+
+        let mut test_file = FileManager::default()
+            .set_header("Old file")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
+        project.initialize_output_files([&mut test_file]).unwrap();
+
+        // Preview of the destination without touching the file system
+        let destination =
+            project.project().archive_destination(test_file.path());
+        assert_eq!(project.path().join("archive/dir/file.dat"), destination);
+        assert!(!destination.exists());
+
+        assert_eq!(
+            destination,
+            project.project().archive_file(test_file.path()).unwrap()
+        );
+        assert_eq!("Old file\n", fs::read_to_string(&destination).unwrap());
+        assert!(test_file.path().exists());
+
+        // Failures are returned instead of panicking
+        assert!(project
+            .project()
+            .archive_file(&project.path().join("dir/missing.dat"))
+            .is_err());
+        project
+            .project_mut()
+            .set_archive_fn(|_, _| Err(io::Error::other("archive is full")));
+        let reason = project
+            .initialize_output_files([&mut FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file")
+                .build()])
+            .unwrap_err();
+        assert!(reason.to_string().contains("archive is full"));
+    }
+
+    #[test]
+    fn per_file_overwrite_type() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let new_files = |header: &str| {
+            [
+                FileManager::default()
+                    .set_header(header)
+                    .set_output_path("dir")
+                    .set_file_name("data")
+                    .build(),
+                FileManager::default()
+                    .set_header(header)
+                    .set_output_path("dir")
+                    .set_file_name("kept")
+                    .set_overwrite_type(OverwriteType::Ignore)
+                    .build(),
+                FileManager::default()
+                    .set_header(header)
+                    .set_output_path("dir")
+                    .set_file_name("critical")
+                    .set_overwrite_type(OverwriteType::Panic)
+                    .build(),
+            ]
+        };
+
+        let mut files = new_files("Old header");
+        project
+            .initialize_output_files(files.iter_mut().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(Some(OverwriteType::Panic), files[2].overwrite_type());
+        assert_eq!(None, files[0].overwrite_type());
+
+        // The project overwrites the data, the kept file is left alone
+        let [mut data, mut kept, mut critical] = new_files("New header");
+        project
+            .initialize_output_files([&mut data, &mut kept])
+            .unwrap();
+        assert!(data.writable());
+        assert!(!kept.writable());
+        assert_eq!("New header\n", fs::read_to_string(data.path()).unwrap());
+        assert_eq!("Old header\n", fs::read_to_string(kept.path()).unwrap());
+
+        // The critical file refuses to be overwritten
+        assert!(project.initialize_output_files([&mut critical]).is_err());
+        assert_eq!(
+            "Old header\n",
+            fs::read_to_string(files[2].path()).unwrap()
+        );
+
+        // Round trip through the serialized form
+        let json = serde_json::to_string(&files[1]).unwrap();
+        let file: FileManager = serde_json::from_str(&json).unwrap();
+        assert_eq!(Some(OverwriteType::Ignore), file.overwrite_type());
+        let file: FileManager = serde_json::from_str(
+            r#"{"name": "file", "overwrite_type": "panic"}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(OverwriteType::Panic), file.overwrite_type());
+    }
+
+    #[test]
+    fn fixed_width_columns() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut columns = ColumnSpec::new([6, 10, 10]);
+        columns.set_alignment(0, Alignment::Left);
+        let mut file = FileManager::default()
+            .set_header("step x y")
+            .set_output_path("dir")
+            .set_file_name("table")
+            .set_columns(&columns)
+            .set_line_ending(LineEnding::Lf)
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+        assert_eq!(Some(&columns), file.columns());
+
+        file.write_columns(&["1", "0.5", "-1.25"]).unwrap();
+        file.write_columns(&["10", "12.3456789012", "3"]).unwrap();
+        assert_eq!(
+            "step           x         y\n\
+            1            0.5     -1.25\n\
+            10    12.3456789         3\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+
+        // Wrong number of values
+        let error = file.write_columns(&["1", "2"]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+
+        // Strict layouts refuse the values that do not fit
+        columns.set_strict(true);
+        let mut strict = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("strict")
+            .set_columns(&columns)
+            .build();
+        project.initialize_output_files([&mut strict]).unwrap();
+        assert!(strict.write_columns(&["1", "12.3456789012", "3"]).is_err());
+        assert_eq!("", fs::read_to_string(strict.path()).unwrap());
+
+        // Files without columns
+        let mut plain = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("plain")
+            .build();
+        project.initialize_output_files([&mut plain]).unwrap();
+        assert!(plain.write_columns(&["1"]).is_err());
+
+        // The comment prefix is taken out of the first column of the header
+        let mut commented = FileManager::default()
+            .set_header("step x y")
+            .set_output_path("dir")
+            .set_file_name("commented")
+            .set_columns(&ColumnSpec::new([6, 10, 10]))
+            .set_comment_char('#')
+            .set_line_ending(LineEnding::Lf)
+            .build();
+        project.initialize_output_files([&mut commented]).unwrap();
+        commented.write_columns(&["1", "0.5", "-1.25"]).unwrap();
+        assert_eq!(
+            "# step         x         y\n     1       0.5     -1.25\n",
+            fs::read_to_string(commented.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn transform_file_lines() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut source = FileManager::default()
+            .set_header("x y")
+            .set_output_path("dir")
+            .set_file_name("raw")
+            .set_line_ending(LineEnding::Lf)
+            .build();
+        let mut dest = FileManager::default()
+            .set_header("x 2y")
+            .set_output_path("dir")
+            .set_file_name("scaled")
+            .set_line_ending(LineEnding::Crlf)
+            .build();
+        project
+            .initialize_output_files([&mut source, &mut dest])
+            .unwrap();
+        for line in ["1 2", "2 -1", "3 4"] {
+            source.append_line(line).unwrap();
+        }
+
+        // Skip the header and the negative values, double the rest
+        let transform = |line: String| {
+            let (x, y) = line.split_once(' ')?;
+            let y: i32 = y.parse().ok()?;
+            (y >= 0).then(|| format!("{x} {}", 2 * y))
+        };
+        source.transform_lines(&dest, transform).unwrap();
+        assert_eq!(
+            "x 2y\r\n1 4\r\n3 8\r\n",
+            fs::read_to_string(dest.path()).unwrap()
+        );
+
+        // In place, the header goes through the function as well
+        source
+            .transform_lines(&source, |line| Some(line.replace(' ', ",")))
+            .unwrap();
+        assert_eq!(
+            "x,y\n1,2\n2,-1\n3,4\n",
+            fs::read_to_string(source.path()).unwrap()
+        );
+
+        // The destination must be writable
+        let mut ignored = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("scaled")
+            .build();
+        ProjectManager::new(
+            project.project().path().to_string(),
+            String::from("dat"),
+            OverwriteType::Ignore,
+        )
+        .initialize_output_files([&mut ignored])
+        .unwrap();
+        let error = source.transform_lines(&ignored, Some).unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        // A non-canonical path to the file itself is transformed in place
+        let mut alias = source.clone();
+        let file_name = source.path().file_name().unwrap().to_owned();
+        alias.path =
+            Some(source.path().parent().unwrap().join(".").join(file_name));
+        source
+            .transform_lines(&alias, |line| Some(line.replace(',', ";")))
+            .unwrap();
+        assert_eq!(
+            "x;y\n1;2\n2;-1\n3;4\n",
+            fs::read_to_string(source.path()).unwrap()
+        );
+
+        // Unbuilt managers are rejected
+        let unbuilt = FileManager::default();
+        let error = unbuilt.transform_lines(&source, Some).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+        let error = source.transform_lines(&unbuilt, Some).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transform_lines_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Keeps the temporary file of the in-place transformation
+        struct NoRenames;
+
+        impl FileSystem for NoRenames {
+            fn rename(&self, _: &Path, _: &Path) -> io::Result<()> {
+                Err(io::Error::from(io::ErrorKind::PermissionDenied))
+            }
+        }
+
+        let mut project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        project.project_mut().set_file_system(NoRenames);
+
+        let mut file = FileManager::default()
+            .set_output_path("")
+            .set_file_name("file")
+            .set_mode(0o600)
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+        file.append_line("1 2").unwrap();
+        file.transform_lines(&file, Some).unwrap_err();
+
+        let temp_path = project.path().join("file.dat.tmp");
+        let mode = fs::metadata(temp_path).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+    }
+
+    #[test]
+    fn thread_safe_hooks() {
+        fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+        assert_send_sync::<ArchiveFn>();
+        assert_send_sync::<PostInitFn>();
+        assert_send_sync::<ProjectManager>();
+        assert_send_sync::<FileManager>();
+    }
+
+    #[test]
+    fn custom_archive() {
+        use std::sync::Arc;
+
+        let mut project =
+            TempProject::new("dat", OverwriteType::Archive).unwrap();
+
+        // Move files to the archive instead of copying them
+        let n_archived = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&n_archived);
+        project
+            .project_mut()
+            .set_archive_fn(move |source, destination| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                fs::rename(source, destination)
+            });
+
+        let mut test_file = FileManager::default()
+            .set_header("Old file")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
+        project.initialize_output_files([&mut test_file]).unwrap();
+        assert_eq!(0, n_archived.load(Ordering::SeqCst));
+
+        let mut test_file_copy = FileManager::default()
+            .set_header("New file")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
+        project
+            .initialize_output_files([&mut test_file_copy])
+            .unwrap();
+        assert_eq!(1, n_archived.load(Ordering::SeqCst));
+
+        assert_eq!(
+            "Old file\n",
+            fs::read_to_string(project.path().join("archive/dir/file.dat"))
+                .unwrap()
+        );
+        assert_eq!(
+            "New file\n",
+            fs::read_to_string(project.path().join("dir/file.dat")).unwrap()
+        );
+    }
+
+    #[test]
+    fn project_from_config_file() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let path = &temp.path().join("config.toml");
+        fs::write(
+            path,
+            "[project]\npath = \"output\"\nextension = \"csv\"\n\
+            overwrite_type = \"Ignore\"\n",
+        )
+        .unwrap();
+
+        let project = ProjectManager::from_config_file(path).unwrap();
+        assert_eq!("output", project.path());
+        assert_eq!(Some("csv"), project.extension());
+
+        fs::write(path, "[other]\n").unwrap();
+        assert!(matches!(
+            ProjectManager::from_config_file(path),
+            Err(Error::MissingKeys { .. })
+        ));
+
+        fs::write(path, "[project]\npath = 1\n").unwrap();
+        assert!(matches!(
+            ProjectManager::from_config_file(path),
+            Err(Error::Deserialize { .. })
+        ));
+    }
+
+    #[test]
+    fn paths_relative_to_config() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path().join("configs");
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[project]\npath = \"results\"\nextension = \"dat\"\n\
+            overwrite_type = \"Panic\"\nresolve_relative_to_config = true\n\
+            [file]\nproject_path = \"data\"\nresolve_relative_to_config = true\n\
+            [cwd]\npath = \"results\"\nextension = \"dat\"\n\
+            overwrite_type = \"Panic\"\n",
+        )
+        .unwrap();
+
+        let config = try_load_config(&path).unwrap();
+
+        let project = ProjectManager::from_config(&config, "project").unwrap();
+        assert_eq!(dir.join("results"), Path::new(project.path()));
+
+        let file = FileManager::from_config(&config, "file").unwrap();
+        assert_eq!(
+            Some(dir.join("data").to_str().unwrap()),
+            file.descriptor.project_path.as_deref()
+        );
+
+        // The functions of config_parse resolve the path as well
+        let expected = Some(dir.join("data").to_str().unwrap().to_string());
+        let file = FileManager::try_from_config(&config, "file").unwrap();
+        assert_eq!(expected, file.descriptor.project_path);
+        let file: FileManager = from_config_or_default(&config, "file");
+        assert_eq!(expected, file.descriptor.project_path);
+
+        #[derive(Deserialize)]
+        struct Outputs {
+            file: FileManager,
+        }
+        let outputs = from_config_root::<Outputs>(&config).unwrap();
+        assert_eq!(expected, outputs.file.descriptor.project_path);
+
+        // Relative to the current directory by default
+        let project = ProjectManager::from_config(&config, "cwd").unwrap();
+        assert_eq!("results", project.path());
+
+        // Without a config file the path is kept as it is
+        let config = Config::from_toml_str(
+            "[file]\nproject_path = \"data\"\n\
+            resolve_relative_to_config = true\n",
+        );
+        let file = FileManager::try_from_config(&config, "file").unwrap();
+        assert_eq!(Some("data"), file.descriptor.project_path.as_deref());
+    }
+
+    #[test]
+    fn attach_file() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut file = FileManager::default()
+            .set_header("Some header")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+
+        let attached = FileManager::attach(file.path()).unwrap();
+        assert!(attached.writable());
+        attached.append_line("1 2 3").unwrap();
+        assert_eq!(
+            "Some header\n1 2 3\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+
+        assert!(matches!(
+            FileManager::attach(&project.path().join("dir/missing.dat")),
+            Err(Error::InvalidPath { .. })
+        ));
+    }
+
+    #[cfg(feature = "glob")]
+    #[test]
+    fn discover_files() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("a")
+                .set_file_name("file_1")
+                .build(),
+            FileManager::default()
+                .set_output_path("a/b")
+                .set_file_name("file_2")
+                .build(),
+            FileManager::default()
+                .set_output_path("a")
+                .set_file_name("file_3")
+                .set_extension("csv")
+                .build(),
+        ];
+        project.initialize_output_files(&mut test_files).unwrap();
+
+        let discovered = project.discover("**/*.dat").unwrap();
+        let paths: Vec<&PathBuf> =
+            discovered.iter().map(FileManager::path).collect();
+        assert_eq!(vec![test_files[1].path(), test_files[0].path()], paths);
+
+        // Attached files keep the project path as the root
+        assert_eq!(
+            Some("a/b"),
+            discovered[0].descriptor.output_path.as_deref()
+        );
+        assert!(discovered.iter().all(FileManager::writable));
+
+        assert!(project.discover("a/*.txt").unwrap().is_empty());
+        assert!(project.discover("[").is_err());
+    }
+
+    #[test]
+    fn progress_events() {
+        let project = TempProject::new("dat", OverwriteType::Ignore).unwrap();
+
+        let mut existing = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("existing")
+            .build();
+        project.initialize_output_files([&mut existing]).unwrap();
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("existing")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("series")
+                .set_series(2)
+                .build(),
+        ];
+
+        let mut events = Vec::new();
+        project
+            .initialize_output_files_with_progress(&mut test_files, |event| {
+                events.push(event)
+            })
+            .unwrap();
+
+        let dir = project.path().join("dir");
+        assert_eq!(
+            vec![
+                ProgressEvent::Started { total: 3 },
+                ProgressEvent::FileCreated {
+                    path: dir.join("series_0.dat"),
+                    index: 0,
+                    total: 3,
+                },
+                ProgressEvent::FileCreated {
+                    path: dir.join("series_1.dat"),
+                    index: 1,
+                    total: 3,
+                },
+                ProgressEvent::Finished,
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn leading_dot_extension() {
+        let new_file = |extension: &str| {
+            FileManager::default()
+                .set_project_path(".")
+                .set_output_path("dir")
+                .set_file_name("file")
+                .set_extension(extension)
+                .build()
+        };
+        assert_eq!(new_file("csv").path(), new_file(".csv").path());
+        assert_eq!(&PathBuf::from("./dir/file.csv"), new_file(".csv").path());
+
+        let mut file = new_file("csv");
+        file.change_extension(".dat");
+        assert_eq!(&PathBuf::from("./dir/file.dat"), file.path());
+
+        let project = ProjectManager::new(
+            String::from("."),
+            String::from(".dat"),
+            OverwriteType::Overwrite,
+        );
+        assert_eq!(Some("dat"), project.extension());
+
+        let config = Config::from_toml_str(
+            r#"
+            [file]
+            project_path = "."
+            output_path = "dir"
+            name = "file"
+            extension = ".dat"
+            "#,
+        );
+        let file = FileManager::try_from_config(&config, "file")
+            .unwrap()
+            .build();
+        assert_eq!(&PathBuf::from("./dir/file.dat"), file.path());
+    }
+
+    #[test]
+    fn extension_precedence() {
+        let mut project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        // Per-file extension wins by default
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_1")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_2")
+                .set_extension("csv")
+                .build(),
+        ];
+        project.initialize_output_files(&mut test_files).unwrap();
+
+        assert!(project.path().join("dir/file_1.dat").exists());
+        assert!(project.path().join("dir/file_2.csv").exists());
+
+        // Project extension wins if forced
+        project.project_mut().set_force_extension(true);
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("forced")
+                .set_file_name("file_1")
+                .build(),
+            FileManager::default()
+                .set_output_path("forced")
+                .set_file_name("file_2")
+                .set_extension("csv")
+                .build(),
+        ];
+        project.initialize_output_files(&mut test_files).unwrap();
+
+        assert!(project.path().join("forced/file_1.dat").exists());
+        assert!(project.path().join("forced/file_2.dat").exists());
+        assert!(!project.path().join("forced/file_2.csv").exists());
+    }
+
+    #[test]
+    fn normalized_paths() {
+        let cases = [
+            ("a/./b/../c.dat", "a/c.dat"),
+            ("./a/b/../../c.dat", "c.dat"),
+            ("../a/../../b.dat", "../../b.dat"),
+            ("/../a/./b.dat", "/a/b.dat"),
+            ("a/..", "."),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                PathBuf::from(expected),
+                normalize_path(Path::new(path))
+            );
+        }
+
+        // The stored path is clean without canonicalization
+        let file = FileManager::default()
+            .set_project_path("project/./runs/..")
+            .set_output_path("data/../out")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_canonicalize(false)
+            .build();
+        assert_eq!(&PathBuf::from("project/out/file.dat"), file.path());
+        assert_eq!(file.path(), &file.normalized_path());
+    }
+
+    #[test]
+    fn absolute_output_path() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        // Absolute directory outside of the project
+        let outside =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let dir = outside.path().join("absolute_output_path");
+        let output_path = dir.join("logs");
+
+        let mut file = FileManager::default()
+            .set_project_path("ignored/project")
+            .set_output_path(output_path.to_str().unwrap())
+            .set_file_name("app")
+            .set_canonicalize(false)
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+        assert_eq!(&output_path.join("app.dat"), file.path());
+        assert!(file.path().exists());
+        assert!(!project.path().join("logs").exists());
+
+        // No project path is needed
+        let file = FileManager::default()
+            .set_output_path(&format!("{}/../logs/", output_path.display()))
+            .set_file_name("app")
+            .set_extension("log")
+            .set_canonicalize(false)
+            .build();
+        assert_eq!(&dir.join("logs/app.log"), file.path());
+
+        // Relative output paths still need the project path
+        let file = FileManager::default()
+            .set_output_path("logs")
+            .set_file_name("app")
+            .set_extension("log")
+            .build();
+        assert!(!file.initialized());
+    }
+
+    #[test]
+    fn staged_project() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path().join("project");
+        let mut project = ProjectManager::new(
+            dir.to_str().unwrap().to_string(),
+            String::from("dat"),
+            OverwriteType::Overwrite,
+        );
+        project.set_staged(true);
+        assert_eq!(temp.path().join("project.staging"), project.staging_path());
+
+        let new_file = |name: &str| {
+            FileManager::default()
+                .set_output_path("data")
+                .set_file_name(name)
+                .set_header("x y")
+                .build()
+        };
+
+        // Nothing reaches the project directory before publishing
+        let mut file = new_file("first");
+        project.initialize_output_files([&mut file]).unwrap();
+        assert!(file
+            .path()
+            .starts_with(project.staging_path().canonicalize().unwrap()));
+        assert!(!dir.exists());
+
+        project.publish().unwrap();
+        assert!(!project.staging_path().exists());
+        assert!(dir.join("data/first.dat").exists());
+
+        // The staging tree replaces the previous outputs as a whole
+        let mut file = new_file("second");
+        project.initialize_output_files([&mut file]).unwrap();
+        project.publish().unwrap();
+        assert!(dir.join("data/second.dat").exists());
+        assert!(!dir.join("data/first.dat").exists());
+
+        // An aborted run keeps the published outputs
+        let mut file = new_file("third");
+        project.initialize_output_files([&mut file]).unwrap();
+        project.abort().unwrap();
+        assert!(!project.staging_path().exists());
+        assert!(dir.join("data/second.dat").exists());
+        assert!(project.publish().is_err());
+
+        // Trees are copied as a whole when they cannot be renamed
+        let copy = temp.path().join("copy");
+        copy_dir_all(&RealFileSystem, &dir, &copy).unwrap();
+        assert!(copy.join("data/second.dat").exists());
+    }
+
+    #[test]
+    fn staged_archive_and_panic() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let root = temp.path().join("project");
+        let staged_project = |overwrite_type| {
+            let mut project = ProjectManager::new(
+                root.to_str().unwrap().to_string(),
+                String::from("dat"),
+                overwrite_type,
+            );
+            project.set_staged(true);
+            project
+        };
+        let run = |project: &ProjectManager, contents: &str| {
+            let mut file = FileManager::default()
+                .set_output_path("data")
+                .set_file_name("f")
+                .build();
+            project.initialize_output_files([&mut file])?;
+            file.append_line(contents).unwrap();
+            Ok::<_, String>(())
+        };
+
+        // Every published run is archived by the next one
+        let project = staged_project(OverwriteType::Archive);
+        for i in 0..3 {
+            run(&project, &format!("run {i}")).unwrap();
+            project.publish().unwrap();
+        }
+        assert_eq!(
+            "run 2\n",
+            fs::read_to_string(root.join("data/f.dat")).unwrap()
+        );
+        assert_eq!(
+            "run 1\n",
+            fs::read_to_string(root.join("archive/data/f.dat")).unwrap()
+        );
+        assert_eq!(
+            root.join("archive/data/f.dat"),
+            project.archive_destination(&root.join("data/f.dat"))
+        );
+
+        // The published file cannot be overwritten
+        let project = staged_project(OverwriteType::Panic);
+        assert!(run(&project, "run 3").is_err());
+        project.abort().unwrap();
+        assert_eq!(
+            "run 2\n",
+            fs::read_to_string(root.join("data/f.dat")).unwrap()
+        );
+
+        // Ignored files are kept through the publication
+        let project = staged_project(OverwriteType::Ignore);
+        let mut file = FileManager::default()
+            .set_output_path("data")
+            .set_file_name("f")
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+        assert!(!file.writable());
+        project.publish().unwrap();
+        assert_eq!(
+            "run 2\n",
+            fs::read_to_string(root.join("data/f.dat")).unwrap()
+        );
+        assert!(root.join("archive/data/f.dat").exists());
+    }
+
+    #[test]
+    fn parse_overwrite_type() {
+        assert_eq!(OverwriteType::Archive, "archive".parse().unwrap());
+        assert_eq!(OverwriteType::Ignore, "IGNORE".parse().unwrap());
+        assert_eq!(
+            OverwriteType::SkipIfUnchanged,
+            "skip_if_unchanged".parse().unwrap()
+        );
+        assert!(matches!(
+            "Append".parse::<OverwriteType>(),
+            Err(Error::InvalidValue { .. })
+        ));
+
+        assert_eq!(OverwriteType::Panic, OverwriteType::try_from(0).unwrap());
+        assert_eq!(OverwriteType::None, OverwriteType::try_from(4).unwrap());
+        assert!(OverwriteType::try_from(6).is_err());
+
+        // Names and codes in the configs
+        #[derive(Deserialize)]
+        struct Settings {
+            overwrite_type: OverwriteType,
+        }
+        let parse = |text: &str| {
+            toml::from_str::<Settings>(text).map(|s| s.overwrite_type)
+        };
+        assert_eq!(
+            OverwriteType::Overwrite,
+            parse("overwrite_type = \"Overwrite\"").unwrap()
+        );
+        assert_eq!(
+            OverwriteType::Overwrite,
+            parse("overwrite_type = \"overwrite\"").unwrap()
+        );
+        assert_eq!(
+            OverwriteType::Archive,
+            parse("overwrite_type = 1").unwrap()
+        );
+        assert!(parse("overwrite_type = 7")
+            .unwrap_err()
+            .to_string()
+            .contains("code must be in [0, 5]"));
+        assert!(parse("overwrite_type = -1").is_err());
+        assert_eq!(
+            OverwriteType::Ignore,
+            serde_json::from_str::<Settings>(r#"{"overwrite_type": 3}"#)
+                .unwrap()
+                .overwrite_type
+        );
+
+        // Serialized by name, which round trips
+        let json = serde_json::to_string(&OverwriteType::SkipIfUnchanged);
+        assert_eq!("\"SkipIfUnchanged\"", json.as_ref().unwrap());
+        assert_eq!(
+            OverwriteType::SkipIfUnchanged,
+            serde_json::from_str::<OverwriteType>(&json.unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn skip_unchanged_files() {
+        let project =
+            TempProject::new("dat", OverwriteType::SkipIfUnchanged).unwrap();
+        let old = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        let contents = [
+            ("same", "x y\n"),
+            ("changed", "a b\n"),
+            ("with_data", "x y\n1 2\n"),
+        ];
+        for (name, contents) in contents {
+            let path = project.path().join(format!("{name}.dat"));
+            fs::write(&path, contents).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        let mut files: Vec<FileManager> = contents
+            .iter()
+            .map(|(name, _)| {
+                FileManager::default()
+                    .set_output_path("")
+                    .set_file_name(name)
+                    .set_header("x y")
+                    .set_line_ending(LineEnding::Lf)
+                    .build()
+            })
+            .collect();
+        let mut created = Vec::new();
+        project
+            .initialize_output_files_with_progress(&mut files, |event| {
+                if let ProgressEvent::FileCreated { path, .. } = event {
+                    created.push(path.file_name().unwrap().to_owned());
+                }
+            })
+            .unwrap();
+
+        // The unchanged file is neither rewritten nor reported, but the data
+        // can still be appended
+        assert_eq!(vec!["changed.dat", "with_data.dat"], created);
+        for file in &files {
+            assert!(file.writable());
+            assert_eq!("x y\n", fs::read_to_string(file.path()).unwrap());
+        }
+        let same = project.path().join("same.dat");
+        assert_eq!(old, fs::metadata(&same).unwrap().modified().unwrap());
+        let changed = project.path().join("changed.dat");
+        assert_ne!(old, fs::metadata(changed).unwrap().modified().unwrap());
+    }
+
+    #[test]
+    fn project_without_extension() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let dir = temp.path().join("project");
+
+        let config = Config::from_toml_str(&format!(
+            "[project]\npath = {:?}\noverwrite_type = \"Overwrite\"\n",
+            dir.to_str().unwrap()
+        ));
+        let mut project =
+            ProjectManager::from_config(&config, "project").unwrap();
+        assert_eq!(None, project.extension());
+        assert!(project
+            .to_string()
+            .contains("Output files keep their own extensions"));
+
+        // Every file keeps its own extension, even if forced
+        project.set_force_extension(true);
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("")
+                .set_file_name("table")
+                .set_extension("csv")
+                .build(),
+            FileManager::default()
+                .set_output_path("")
+                .set_file_name("log")
+                .set_extension("txt")
+                .build(),
+        ];
+        project.initialize_output_files(&mut test_files).unwrap();
+
+        assert!(dir.join("table.csv").exists());
+        assert!(dir.join("log.txt").exists());
+
+        // A file without an extension has no path to resolve
+        let mut file = FileManager::default()
+            .set_output_path("")
+            .set_file_name("data")
+            .build();
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                project.initialize_output_files([&mut file])
+            }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn overwrite_conflicts() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut test_file = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("file_1")
+            .build();
+        let mut test_series = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(2)
+            .build();
+
+        // Nothing exists yet
+        assert!(project.conflicts(&[&test_file, &test_series]).is_empty());
+
+        project
+            .initialize_output_files([&mut test_file, &mut test_series])
+            .unwrap();
+
+        let test_file_copy = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("file_1")
+            .build();
+        let test_series_copy = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(2)
+            .build();
+        let test_file_new = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("file_2")
+            .build();
+
+        let conflicts = project.conflicts(&[
+            &test_file_copy,
+            &test_series_copy,
+            &test_file_new,
+        ]);
+
+        let dir = project.path().join("dir");
+        assert_eq!(
+            vec![
+                dir.join("file_1.dat"),
+                dir.join("series_0.dat"),
+                dir.join("series_1.dat"),
+            ],
+            conflicts
+        );
+
+        // The files were not modified
+        assert!(!test_file_copy.initialized());
+        assert!(!dir.join("file_2.dat").exists());
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        // Fails with the error kind for the first `failures` attempts
+        let flaky = |kind: io::ErrorKind, failures: u32| {
+            let mut attempts = 0;
+            move || {
+                attempts += 1;
+                if attempts <= failures {
+                    Err(io::Error::from(kind))
+                } else {
+                    Ok(attempts)
+                }
+            }
+        };
+
+        let retry = RetryPolicy::new(3, Duration::from_millis(1));
+        assert_eq!(3, retry.run(flaky(io::ErrorKind::WouldBlock, 2)).unwrap());
+        assert!(retry.run(flaky(io::ErrorKind::ResourceBusy, 4)).is_err());
+
+        // Permanent errors are not retried
+        let error = retry
+            .run(flaky(io::ErrorKind::PermissionDenied, 1))
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        // No retries by default
+        let error = RetryPolicy::default()
+            .run(flaky(io::ErrorKind::Interrupted, 1))
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::Interrupted, error.kind());
+
+        // The backoff doubles up to the cap without overflowing
+        assert_eq!(Duration::from_millis(4), retry.backoff(2));
+        let slow = RetryPolicy::new(u32::MAX, Duration::MAX);
+        assert_eq!(u64::MAX, slow.backoff_ms);
+        assert_eq!(RetryPolicy::MAX_BACKOFF, slow.backoff(0));
+        assert_eq!(RetryPolicy::MAX_BACKOFF, retry.backoff(u32::MAX));
+
+        // The policy can be set in the project table of the config
+        let config = Config::from_toml_str(
+            r#"
+            [project]
+            path = "output"
+            extension = "dat"
+            overwrite_type = "Overwrite"
+            retry = { retries = 3, backoff_ms = 1 }
+            "#,
+        );
+        let project = ProjectManager::from_config(&config, "project").unwrap();
+        assert_eq!(retry, project.retry);
+    }
+
+    #[test]
+    fn simulated_file_system() {
+        // Fails to open files with the error kind for the first `failures`
+        // attempts
+        struct FlakyOpen {
+            kind: io::ErrorKind,
+            failures: usize,
+            attempts: AtomicUsize,
+        }
+
+        impl FileSystem for FlakyOpen {
+            fn open(
+                &self,
+                path: &Path,
+                options: &OpenOptions,
+            ) -> io::Result<fs::File> {
+                let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+                if attempt < self.failures {
+                    Err(io::Error::from(self.kind))
+                } else {
+                    RealFileSystem.open(path, options)
+                }
+            }
+        }
+
+        let mut project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let new_file = || {
+            FileManager::default()
+                .set_output_path("")
+                .set_file_name("file")
+                .build()
+        };
+
+        // Transient failures are retried
+        project
+            .project_mut()
+            .set_retry_policy(RetryPolicy::new(2, Duration::from_millis(1)))
+            .set_file_system(FlakyOpen {
+                kind: io::ErrorKind::Interrupted,
+                failures: 2,
+                attempts: Default::default(),
+            });
+        let mut file = new_file();
+        project.initialize_output_files([&mut file]).unwrap();
+        assert!(file.writable());
+        assert!(file.path().exists());
+        fs::remove_file(file.path()).unwrap();
+
+        // A full disk aborts the initialization
+        project.project_mut().set_file_system(FlakyOpen {
+            kind: io::ErrorKind::StorageFull,
+            failures: 1,
+            attempts: Default::default(),
+        });
+        let mut file = new_file();
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                project.initialize_output_files([&mut file])
+            }));
+        assert!(result.is_err());
+        assert!(!project.path().join("file.dat").exists());
+
+        // The standalone initialization takes the filesystem as well
+        let mut file = new_file();
+        file.set_project_path(project.path().to_str().unwrap())
+            .set_extension("dat");
+        let file_system = FlakyOpen {
+            kind: io::ErrorKind::PermissionDenied,
+            failures: 1,
+            attempts: Default::default(),
+        };
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                file.initialize_output_in(&file_system)
+            }));
+        assert!(result.is_err());
+        assert_eq!(1, file_system.attempts.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn project_clock() {
+        let mut project = ProjectManager::new(
+            String::from("."),
+            String::from("dat"),
+            OverwriteType::Overwrite,
+        );
+        let before = SystemTime::now();
+        assert!(project.now() >= before);
+
+        let fixed = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        project.set_clock(move || fixed);
+        assert_eq!(fixed, project.now());
+    }
+
+    #[test]
+    fn simulated_file_operations() {
+        // Denies moving and copying files
+        struct NoMoves;
+
+        impl FileSystem for NoMoves {
+            fn copy(&self, _: &Path, _: &Path) -> io::Result<u64> {
+                Err(io::Error::from(io::ErrorKind::PermissionDenied))
+            }
+
+            fn rename(&self, _: &Path, _: &Path) -> io::Result<()> {
+                Err(io::Error::from(io::ErrorKind::PermissionDenied))
+            }
+        }
+
+        let mut project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        project.project_mut().set_file_system(NoMoves);
+
+        // The initialized files keep the filesystem of the project
+        let mut file = FileManager::default()
+            .set_output_path("")
+            .set_file_name("file")
+            .set_max_size(5)
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+        file.append_line("0123456789").unwrap();
+        let error = file.append_line("0123456789").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        let error = file.import_from(Path::new("Cargo.toml")).unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        // Rewriting a file in place replaces it with a rename
+        let error = file.change_header("x").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+        let error = file.transform_lines(&file, Some).unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        // A standalone file takes its own filesystem
+        let mut file = FileManager::default()
+            .set_project_path(project.path().to_str().unwrap())
+            .set_output_path("")
+            .set_file_name("imported")
+            .set_extension("dat")
+            .build();
+        file.set_file_system(NoMoves);
+        let error = file.import_from(Path::new("Cargo.toml")).unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+
+        // A failed publish leaves the staging directory in place
+        let mut staged = ProjectManager::new(
+            project.path().join("staged").to_str().unwrap().to_string(),
+            String::from("dat"),
+            OverwriteType::Overwrite,
+        );
+        staged.set_staged(true).set_file_system(NoMoves);
+        let mut file = FileManager::default()
+            .set_output_path("")
+            .set_file_name("file")
+            .build();
+        staged.initialize_output_files([&mut file]).unwrap();
+
+        let error = staged.publish().unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, error.kind());
+        assert!(staged.staging_path().join("file.dat").exists());
+        assert!(!project.path().join("staged").exists());
+
+        staged.abort().unwrap();
+        assert!(!staged.staging_path().exists());
+    }
+
+    #[test]
+    fn scaffold_project() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let root = temp.path();
+        let project = ProjectManager::new(
+            root.join("output").to_str().unwrap().to_string(),
+            String::from("dat"),
+            OverwriteType::Overwrite,
+        );
+        let gitignore = root.join("output/.gitignore");
+
+        project.scaffold(&[]).unwrap();
+        assert_eq!("*\n", fs::read_to_string(&gitignore).unwrap());
+
+        // Existing .gitignore is kept unless asked
+        project.scaffold(&["*.dat", "archive/"]).unwrap();
+        assert_eq!("*\n", fs::read_to_string(&gitignore).unwrap());
+
+        project
+            .scaffold_with_overwrite(&["*.dat", "archive/"], true)
+            .unwrap();
+        assert_eq!(
+            "*.dat\narchive/\n",
+            fs::read_to_string(&gitignore).unwrap()
+        );
+    }
+
+    #[test]
+    fn expected_size() {
+        let file = FileManager::default()
+            .set_file_name("file")
+            .set_expected_size(1000)
+            .clone();
+        let series = FileManager::default()
+            .set_file_name("series")
+            .set_series(3)
+            .set_expected_size(10)
+            .clone();
+        let unknown = FileManager::default().set_file_name("unknown").clone();
+
+        assert_eq!(Some(1000), file.reserve_hint());
+        assert_eq!(
+            1030,
+            ProjectManager::total_expected_size(&[&file, &series, &unknown])
+        );
+
+        // Large hints saturate instead of overflowing
+        let large = FileManager::default()
+            .set_file_name("large")
+            .set_series(3)
+            .set_expected_size(u64::MAX / 2)
+            .clone();
+        assert_eq!(
+            u64::MAX,
+            ProjectManager::total_expected_size(&[&file, &large])
+        );
+
+        #[cfg(feature = "fs2")]
+        {
+            let project =
+                TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+            assert!(project.check_disk_space(&[&file, &series]).is_ok());
+
+            let huge = FileManager::default()
+                .set_file_name("huge")
+                .set_expected_size(u64::MAX)
+                .clone();
+            assert!(matches!(
+                project.check_disk_space(&[&huge]),
+                Err(Error::InsufficientSpace { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn post_init_hook() {
+        let mut project =
+            TempProject::new("dat", OverwriteType::Ignore).unwrap();
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_seen = seen.clone();
+        project.project_mut().set_post_init(move |file| {
+            hook_seen.lock().unwrap().push(file.path().clone());
+            fs::metadata(file.path()).map(|_| ())
+        });
+
+        let new_file = |name: &str| {
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name(name)
+                .build()
+        };
+
+        let mut file = new_file("file");
+        project.initialize_output_files([&mut file]).unwrap();
+        assert_eq!(vec![file.path().clone()], *seen.lock().unwrap());
+
+        // Ignored files are not post-processed
+        let mut ignored = new_file("file");
+        project.initialize_output_files([&mut ignored]).unwrap();
+        assert_eq!(1, seen.lock().unwrap().len());
+
+        // Errors of the hook abort the initialization
+        project
+            .project_mut()
+            .set_post_init(|_| Err(io::Error::other("index is offline")));
+        let mut other = new_file("other");
+        let reason = project.initialize_output_files([&mut other]).unwrap_err();
+        assert!(reason.contains("index is offline"));
+    }
+
+    #[test]
+    fn project_plan() {
+        let project = TempProject::new("dat", OverwriteType::Archive).unwrap();
+        fs::create_dir_all(project.path().join("dir")).unwrap();
+        fs::write(project.path().join("dir/series_1.dat"), "").unwrap();
+
+        let file = FileManager::default()
+            .set_header("x y")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .clone();
+        let series = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(2)
+            .set_overwrite_type(OverwriteType::Panic)
+            .clone();
+        let stdout = FileManager::stdout();
+
+        let plan = project.plan(&[&file, &series, &stdout]);
+        assert_eq!(OverwriteType::Archive, plan.files()[0].overwrite_type());
+        assert_eq!(OverwriteType::Panic, plan.files()[1].overwrite_type());
+        let dir = project.path().join("dir");
+        assert_eq!(vec![dir.join("file.dat")], plan.files()[0].paths());
+        assert_eq!(vec![dir.join("series_1.dat")], plan.files()[1].existing());
+        assert!(!file.initialized());
+
+        let lines: Vec<String> =
+            plan.to_string().lines().map(String::from).collect();
+        assert_eq!(5, lines.len());
+        assert!(
+            lines[0].ends_with("(extension .dat, overwrite_type = Archive)")
+        );
+        assert!(lines[1].ends_with("file.dat"));
+        assert!(lines[2].ends_with("series_0.dat (overwrite_type = Panic)"));
+        assert!(
+            lines[3].ends_with("series_1.dat (exists, overwrite_type = Panic)")
+        );
+        assert_eq!("  <stdout>", lines[4]);
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!("Archive", json["overwrite_type"]);
+        assert_eq!("Panic", json["files"][1]["overwrite_type"]);
+        assert_eq!("x y", json["files"][0]["header"]);
+    }
+
+    #[test]
+    fn initialize_projects() {
+        let first = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let second = TempProject::new("dat", OverwriteType::Panic).unwrap();
+
+        let new_file = |name: &str| {
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name(name)
+                .build()
+        };
+
+        // The second project refuses to overwrite an existing file
+        fs::create_dir_all(second.path().join("dir")).unwrap();
+        fs::write(second.path().join("dir/taken.dat"), "").unwrap();
+
+        let mut file_1 = new_file("file_1");
+        let mut file_2 = new_file("file_2");
+        let mut taken = new_file("taken");
+        let mut file_3 = new_file("file_3");
+
+        let results = ProjectManager::initialize_many([
+            (first.project(), vec![&mut file_1, &mut file_2]),
+            (second.project(), vec![&mut taken]),
+            (first.project(), vec![&mut file_3]),
+        ]);
+
+        // Every project has its own result, even with a shared root
+        assert_eq!(3, results.len());
+        assert_eq!(first.project().path(), &results[0].0);
+        assert!(results[0].1.is_ok());
+        assert_eq!(second.project().path(), &results[1].0);
+        assert!(results[1].1.is_err());
+        assert_eq!(first.project().path(), &results[2].0);
+        assert!(results[2].1.is_ok());
+        assert!(first.path().join("dir/file_1.dat").exists());
+        assert!(first.path().join("dir/file_2.dat").exists());
+        assert!(first.path().join("dir/file_3.dat").exists());
+        assert!(!taken.writable());
+    }
+
+    #[test]
+    fn duplicate_paths() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_1")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_2")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_1")
+                .build(),
+        ];
+
+        let result = project.initialize_output_files(&mut test_files);
+        match result {
+            Err(reason) => {
+                assert!(reason.starts_with("Duplicate output paths"));
+                assert!(reason.contains("files #0 and #2"), "{reason}");
+            }
+            Ok(()) => panic!("Duplicate output paths were not detected"),
+        }
+
+        // Nothing was written
+        assert!(!project.path().join("dir").exists());
+
+        // Series members are checked as well
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file")
+                .set_series(3)
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file_2")
+                .build(),
+        ];
+
+        assert!(project.initialize_output_files(&mut test_files).is_err());
+        assert!(!project.path().join("dir").exists());
+    }
+
+    #[test]
+    fn ignore_files() {
+        // Setup test project directory tree
+        let project = TempProject::new("dat", OverwriteType::Ignore).unwrap();
+        let project_manager = project.project();
+
+        let mut test_file_1 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .set_extension("dat")
+            .build();
+
+        let mut test_file_2 = FileManager::default()
+            .set_output_path("dir_1")
+            .set_file_name("file_2")
+            .set_extension("csv")
+            .build();
+
+        let mut test_file_3 = FileManager::default()
+            .set_output_path("dir_2")
+            .set_file_name("file_3")
+            .set_extension("txt")
+            .build();
+
+        let mut test_file_4 = FileManager::default()
+            .set_output_path("dir_3")
+            .set_file_name("file_4")
+            .build();
+
+        let files = vec![
+            &mut test_file_1,
+            &mut test_file_2,
+            &mut test_file_3,
+            &mut test_file_4,
+        ];
+
+        if let Err(reason) = project_manager.initialize_output_files(files) {
+            panic!(
+                "Could not initialize output files for Ignore test: \
+                {reason}"
+            )
+        }
+
+        let mut test_file_1_copy = FileManager::default()
+            .set_header("New file_1")
+            .set_output_path("dir_1")
+            .set_file_name("file_1")
+            .set_extension("dat")
+            .build();
+
+        let files = vec![&mut test_file_1_copy];
+
+        if let Err(reason) = project_manager.initialize_output_files(files) {
+            panic!(
+                "Could not initialize output files for Ignore test: \
+                {reason}"
+            )
+        }
+
+        // Verify that correct files were initialized
+        assert!(
+            project.path().join("dir_1/file_1.dat").exists(),
+            "file_1.dat was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_1/file_2.csv").exists(),
+            "file_2.csv was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_2/file_3.txt").exists(),
+            "file_3.txt was not created!"
+        );
+
+        assert!(
+            project.path().join("dir_3/file_4.dat").exists(),
+            "file_4.dat was not created!"
+        );
+
+        // Verify final states of the FileManager
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_1.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_1.path(),
+            "The path of file_1.dat does not match the expected one."
+        );
+
+        assert!(!test_file_1_copy.writable(), "{:?}", test_file_1_copy);
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_1/file_2.csv")
+                .canonicalize()
+                .unwrap(),
+            *test_file_2.path(),
+            "The path of file_2.csv does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_2/file_3.txt")
+                .canonicalize()
+                .unwrap(),
+            *test_file_3.path(),
+            "The path of file_3.txt does not match the expected one."
+        );
+
+        assert_eq!(
+            project
+                .path()
+                .join("dir_3/file_4.dat")
+                .canonicalize()
+                .unwrap(),
+            *test_file_4.path(),
+            "The path of file_4.dat does not match the expected one."
+        );
+    }
+
+    #[test]
+    fn define_builder() {
+        let file = FileManager::default()
+            .set_header("Some header")
+            .set_project_path(".")
+            .set_output_path("test")
+            .set_file_name("test")
+            .set_extension("dat")
+            .build();
+
+        assert_eq!(
+            FileManager {
+                descriptor: FileDescriptor {
+                    header: Some(String::from("Some header")),
+                    project_path: Some(String::from(".")),
+                    output_path: Some(String::from("test")),
+                    name: Some(String::from("test")),
+                    extension: Some(String::from("dat")),
+                    ..FileDescriptor::default()
+                },
+                path: Some(PathBuf::from("./test/test.dat")),
+                writable: false,
+                cleanup_on_drop: CleanupOnDrop::default(),
+                created_dirs: Vec::new(),
+                file_system: None
+            },
+            file
+        );
+    }
+
+    #[cfg(feature = "locking")]
+    #[test]
+    fn lock_file() {
+        let mut file = FileManager::default()
+            .set_project_path("test_lock")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .build();
+
+        file.initialize_output();
+
+        {
+            let _lock = file.lock_exclusive().unwrap();
+
+            // The file cannot be locked twice
+            assert!(file.try_lock_exclusive().is_err());
+        }
+
+        // The lock is released once the guard is dropped
+        assert!(file.try_lock_exclusive().is_ok());
+
+        // The locks of every file in series are probed on initialization
+        // only if requested
+        let new_series = |lock_on_init: bool| {
+            FileManager::default()
+                .set_project_path("test_lock")
+                .set_output_path("dir")
+                .set_file_name("series")
+                .set_extension("dat")
+                .set_series(2)
+                .set_lock_on_init(lock_on_init)
+                .build()
+        };
+        let mut series = new_series(false);
+        series.initialize_output();
+        assert!(!series.lock_on_init());
+        {
+            let _lock = series.clone_for_index(1).lock_exclusive().unwrap();
+            new_series(false).initialize_output();
+
+            let result = std::panic::catch_unwind(|| {
+                new_series(true).initialize_output()
+            });
+            assert!(result.is_err());
+        }
+        new_series(true).initialize_output();
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_lock/") {
+            panic!(
+                "Cannot remove project directory ./test_lock/: {:?}",
+                reason
+            );
+        }
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn file_checksum() {
+        let mut file = FileManager::default()
+            .set_header("Some header")
+            .set_project_path("test_checksum")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .build();
+
+        file.initialize_output();
+
+        // SHA-256 of "Some header\n"
+        let expected =
+            "241379223fb9aebffac786d7333b243b4e129fc89ef104fe51e674d9fe3adcb7";
+
+        assert_eq!(expected, file.checksum().unwrap());
+        assert!(file.verify_checksum(expected).unwrap());
+        assert!(!file.verify_checksum("0000").unwrap());
+
+        let mut series = FileManager::default()
+            .set_header("Some header")
+            .set_project_path("test_checksum")
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_extension("dat")
+            .set_series(3)
+            .build();
+
+        series.initialize_output();
+
+        let checksums = series.series_checksums().unwrap();
+        assert_eq!(3, checksums.len());
+        assert!(checksums.values().all(|checksum| checksum == expected));
+
+        assert!(file.series_checksums().is_err());
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_checksum/") {
+            panic!(
+                "Cannot remove project directory ./test_checksum/: {:?}",
+                reason
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut file = FileManager::default()
+            .set_project_path("test_permissions")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_mode(0o640)
+            .set_dir_mode(0o750)
+            .build();
+
+        file.initialize_output();
+
+        let file_mode = fs::metadata("./test_permissions/dir/file.dat")
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o640, file_mode & 0o777);
+
+        let dir_mode = fs::metadata("./test_permissions/dir")
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o750, dir_mode & 0o777);
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_permissions/") {
+            panic!(
+                "Cannot remove project directory ./test_permissions/: {:?}",
+                reason
+            );
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn write_csv_records() {
+        #[derive(Serialize)]
+        struct Record {
+            x: u32,
+            y: f64,
+        }
+
+        let mut file = FileManager::default()
+            .set_header("x,y")
+            .set_project_path("test_csv")
+            .set_output_path("dir")
+            .set_file_name("comma")
+            .set_extension("csv")
+            .build();
+
+        file.initialize_output();
+        file.write_record(&Record { x: 1, y: 0.5 }).unwrap();
+        file.write_record(&Record { x: 2, y: 1.5 }).unwrap();
+
+        assert_eq!(
+            "x,y\n1,0.5\n2,1.5\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+
+        let mut file = FileManager::default()
+            .set_header("x;y")
+            .set_project_path("test_csv")
+            .set_output_path("dir")
+            .set_file_name("semicolon")
+            .set_extension("csv")
+            .set_delimiter(b';')
+            .build();
+
+        file.initialize_output();
+        file.write_record(&Record { x: 1, y: 0.5 }).unwrap();
+
+        assert_eq!("x;y\n1;0.5\n", fs::read_to_string(file.path()).unwrap());
+
+        // Compressed records are appended as new streams
+        #[cfg(feature = "gzip")]
+        {
+            let mut file = FileManager::default()
+                .set_header("x,y")
+                .set_project_path("test_csv")
+                .set_output_path("dir")
+                .set_file_name("compressed")
+                .set_extension("csv")
+                .set_compression(Compression::Gzip)
+                .build();
+
+            file.initialize_output();
+            file.write_record(&Record { x: 1, y: 0.5 }).unwrap();
+            file.write_record(&Record { x: 2, y: 1.5 }).unwrap();
+
+            assert!(!fs::read(file.path()).unwrap().ends_with(b"1.5\n"));
+            let mut contents = String::new();
+            file.open_reader()
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!("x,y\n1,0.5\n2,1.5\n", contents);
+        }
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_csv/") {
+            panic!("Cannot remove project directory ./test_csv/: {:?}", reason);
+        }
+    }
+
+    #[test]
+    fn read_buffer() {
+        use io::BufRead;
+
+        let mut file = FileManager::default()
+            .set_header("Some header")
+            .set_project_path("test_read_buffer")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .build();
+
+        file.initialize_output();
+        writeln!(file.open_file(), "1 2 3").unwrap();
+
+        // Reading does not depend on the write permissions
+        file.change_write_permission(false);
+
+        let lines: Vec<String> = file
+            .open_read_buffer()
+            .unwrap()
+            .lines()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec!["Some header", "1 2 3"], lines);
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_read_buffer/") {
+            panic!(
+                "Cannot remove project directory ./test_read_buffer/: {:?}",
+                reason
+            );
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_file() {
+        use io::BufRead;
+
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut file = FileManager::default()
+            .set_header("Some header")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_compression(Compression::Gzip)
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+
+        assert_eq!(project.path().join("dir/file.dat.gz"), *file.path());
+
+        file.append_line("1 2 3").unwrap();
+        file.append_line("4 5 6").unwrap();
+
+        let lines: Vec<String> = file
+            .open_reader()
+            .unwrap()
+            .lines()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec!["Some header", "1 2 3", "4 5 6"], lines);
+
+        // Raw writes would corrupt the compressed streams
+        assert_eq!(
+            io::ErrorKind::Unsupported,
+            file.try_open_file().unwrap_err().kind()
+        );
+        assert!(file.try_writer().unwrap().open_file().is_err());
+
+        // Buffers append a member as well
+        {
+            let mut buffer = file.open_buffer();
+            writeln!(buffer, "7 8 9").unwrap();
+        }
+
+        // Each write is a complete gzip member, a single-member decoder
+        // only sees the header
+        use io::Read;
+        let bytes = fs::read(file.path()).unwrap();
+        let mut contents = String::new();
+        flate2::read::MultiGzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("Some header\n1 2 3\n4 5 6\n7 8 9\n", contents);
+
+        let mut first_member = String::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut first_member)
+            .unwrap();
+        assert_eq!("Some header\n", first_member);
+    }
+
+    #[test]
+    fn display_file() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let project_path = temp.path().to_str().unwrap();
+
+        let mut file = FileManager::default()
+            .set_project_path(project_path)
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_canonicalize(false)
+            .build();
+        assert_eq!(
+            format!("{} (no header, read-only)", file.path().display()),
+            file.to_string()
+        );
+
+        let series = FileManager::default()
+            .set_header("x y")
+            .set_project_path(project_path)
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_extension("dat")
+            .set_series(3)
+            .set_canonicalize(false)
+            .build();
+        assert_eq!(
+            format!(
+                "{} (series of 3 files, header \"x y\", read-only)",
+                series.path().display()
+            ),
+            series.to_string()
+        );
+
+        assert_eq!(
+            "<unresolved path> (no header, read-only)",
+            FileManager::default().to_string()
+        );
+
+        file.initialize_output();
+        assert!(file.to_string().ends_with("(no header, writable)"));
+    }
+
+    #[test]
+    fn parallel_series() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut series = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(4)
+            .build();
+        project.initialize_output_files([&mut series]).unwrap();
+
+        std::thread::scope(|scope| {
+            for index in 0..4 {
+                let file = series.clone_for_index(index);
+                scope.spawn(move || {
+                    assert!(file.writable());
+                    file.append_line(&format!("worker {index}")).unwrap();
+                });
+            }
+        });
+
+        for index in 0..4 {
+            let path = project.path().join(format!("dir/series_{index}.dat"));
+            assert_eq!(
+                format!("worker {index}\n"),
+                fs::read_to_string(path).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn series_headers() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let headers: Vec<String> = [0.5, 1.0, 1.5]
+            .iter()
+            .map(|temperature| format!("# temperature = {temperature}"))
+            .collect();
+
+        let mut series = FileManager::default()
+            .set_header("# common header")
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(3)
+            .set_series_headers(headers.clone())
+            .try_build()
+            .unwrap();
+        project.initialize_output_files([&mut series]).unwrap();
+
+        assert!(series.is_series());
+        assert_eq!(Some(3), series.series_count());
+        series.change_file_index(1);
+        assert_eq!(Some(1), series.series_index());
+        assert!(!FileManager::default().is_series());
+        assert_eq!(None, FileManager::default().series_count());
+
+        for (index, header) in headers.iter().enumerate() {
+            let path = project.path().join(format!("dir/series_{index}.dat"));
+            assert_eq!(
+                format!("{header}\n"),
+                fs::read_to_string(path).unwrap()
+            );
+        }
+
+        // Every file of the series needs a header
+        let result = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("short")
+            .set_series(4)
+            .set_series_headers(headers.clone())
+            .try_build();
+        assert!(matches!(result, Err(Error::InvalidSeries { .. })));
+
+        // build is lenient, the files without a header stay empty
+        let mut short = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("short")
+            .set_series(4)
+            .set_series_headers(headers.clone())
+            .build();
+        project.initialize_output_files([&mut short]).unwrap();
+        let path = project.path().join("dir/short_3.dat");
+        assert_eq!("", fs::read_to_string(path).unwrap());
+
+        let result = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("single")
+            .set_series_headers(headers)
+            .try_build();
+        assert!(matches!(result, Err(Error::InvalidSeries { .. })));
+    }
+
+    #[test]
+    fn file_preamble() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut file = FileManager::default()
+            .set_preamble(vec![
+                String::from("units: K, J"),
+                String::from("run: 42"),
+            ])
+            .set_comment_char('#')
+            .set_header("temperature energy\n(K) (J)")
+            .set_output_path("dir")
+            .set_file_name("preamble")
+            .try_build()
+            .unwrap();
+        project.initialize_output_files([&mut file]).unwrap();
+        file.append_line("1.0 2.0").unwrap();
+
+        let path = file.path().clone();
+        let read = || fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            "# units: K, J\n# run: 42\n# temperature energy\n# (K) (J)\n\
+            1.0 2.0\n",
+            read()
+        );
+
+        // The whole block is replaced when the header changes
+        file.change_header("temperature").unwrap();
+        assert_eq!(
+            "# units: K, J\n# run: 42\n# temperature\n1.0 2.0\n",
+            read()
+        );
+    }
+
+    #[test]
+    fn multi_line_header() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut files = [
+            FileManager::default()
+                .set_header("time energy\r\n(s) (J)\n")
+                .set_output_path("dir")
+                .set_file_name("multi")
+                .build(),
+            FileManager::default()
+                .set_header("time energy")
+                .set_output_path("dir")
+                .set_file_name("single")
+                .build(),
+        ];
+        project.initialize_output_files(files.iter_mut()).unwrap();
+
+        assert_eq!(2, files[0].header_rows());
+        assert_eq!(1, files[1].header_rows());
+        assert_eq!(0, FileManager::default().header_rows());
+        assert_eq!(
+            "time energy\n(s) (J)\n",
+            fs::read_to_string(files[0].path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn strict_project_path() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let build = |project_path: &Path| {
+            FileManager::default()
+                .set_project_path(project_path.to_str().unwrap())
+                .set_output_path("dir")
+                .set_file_name("file")
+                .set_extension("dat")
+                .set_strict_project_path(true)
+                .try_build()
+        };
 
-    /// Opens a file to append the data
-    pub fn open_file(&self) -> fs::File {
-        if self.writable() {
-            match OpenOptions::new().append(true).open(self.path().as_path()) {
-                Ok(file) => file,
-                Err(reason) => panic!(
-                    "Could not open file {:?}: {:?}",
-                    self.path(),
-                    reason
-                ),
+        assert!(build(project.path()).is_ok());
+
+        // Missing directory inside an existing one
+        match build(&project.path().join("missing")) {
+            Err(Error::InvalidPath { reason, .. }) => {
+                assert!(reason.contains("would be created"), "{reason}")
             }
-        } else {
-            panic!(
-                "{}",
-                io::Error::new(
-                    io::ErrorKind::PermissionDenied,
-                    format!(
-                        "File {:?} does not have write permissions",
-                        self.path()
-                    ),
-                )
-            )
+            _ => panic!("Missing project directory was not detected"),
         }
-    }
 
-    /// Opens a file in a buffer to append the data (for larger arrays)
-    pub fn open_buffer(&self) -> io::BufWriter<fs::File> {
-        io::BufWriter::new(self.open_file())
+        // Missing root
+        match build(&project.path().join("missing/project")) {
+            Err(Error::InvalidPath { reason, .. }) => {
+                assert!(reason.contains("neither does its parent"), "{reason}")
+            }
+            _ => panic!("Missing project root was not detected"),
+        }
+
+        // build stays lenient
+        assert!(FileManager::default()
+            .set_project_path(project.path().join("missing").to_str().unwrap())
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_strict_project_path(true)
+            .build()
+            .path
+            .is_some());
+
+        // Lenient mode by default
+        assert!(FileManager::default()
+            .set_project_path(project.path().join("missing").to_str().unwrap())
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .try_build()
+            .is_ok());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::fs::remove_dir_all;
+    #[test]
+    fn stream_target() {
+        let project = TempProject::new("dat", OverwriteType::Panic).unwrap();
 
-    use super::*;
+        let mut stdout = FileManager::stdout();
+        let mut stderr = FileManager::stderr();
+        assert!(stdout.is_stream());
+        assert_eq!("<stdout> (no header, read-only)", stdout.to_string());
+
+        assert!(project.conflicts(&[&stdout, &stderr]).is_empty());
+        project
+            .initialize_output_files([&mut stdout, &mut stderr])
+            .unwrap();
+
+        // Nothing is created on disk
+        assert_eq!(0, fs::read_dir(project.path()).unwrap().count());
+        assert!(stdout.writable());
+
+        stdout.open_writer().unwrap().flush().unwrap();
+        stderr.open_writer().unwrap().flush().unwrap();
+        assert_eq!(
+            io::ErrorKind::Unsupported,
+            stdout.try_open_file().unwrap_err().kind()
+        );
+    }
 
     #[test]
-    fn overwrite_files() {
-        // Setup test project directory tree
-        let project_manager = ProjectManager {
-            path: "test_overwrite".to_owned(),
-            extension: "dat".to_owned(),
-            overwrite_type: OverwriteType::Overwrite,
-        };
+    fn flush_buffer() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
 
-        let mut test_file_1 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
-            .set_extension("dat")
+        let mut file = FileManager::default()
+            .set_header("Some header")
+            .set_output_path("dir")
+            .set_file_name("file")
             .build();
+        project.initialize_output_files([&mut file]).unwrap();
 
-        let mut test_file_2 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_2")
-            .set_extension("csv")
-            .build();
+        {
+            let mut buffer = file.open_buffer();
+            writeln!(buffer, "1 2 3").unwrap();
+            writeln!(buffer, "4 5 6").unwrap();
+
+            // Nothing reaches the file before the buffer is flushed
+            assert_eq!(
+                "Some header\n",
+                fs::read_to_string(file.path()).unwrap()
+            );
+        }
 
-        let mut test_file_3 = FileManager::default()
-            .set_output_path("dir_2")
-            .set_file_name("file_3")
-            .set_extension("txt")
-            .build();
+        assert_eq!(
+            "Some header\n1 2 3\n4 5 6\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+        file.flush().unwrap();
+    }
 
-        let mut test_file_4 = FileManager::default()
-            .set_output_path("dir_3")
-            .set_file_name("file_4")
+    #[test]
+    fn touch_file() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let new_file = || {
+            FileManager::default()
+                .set_header("Some header")
+                .set_project_path(project.path().to_str().unwrap())
+                .set_output_path("nested/dir")
+                .set_file_name("log")
+                .set_extension("dat")
+                .build()
+        };
+
+        let mut file = new_file();
+        file.touch().unwrap();
+        assert!(file.writable());
+        file.append_line("first run").unwrap();
+
+        // Touching again keeps the contents
+        let mut file = new_file();
+        file.touch().unwrap();
+        file.append_line("second run").unwrap();
+        assert_eq!(
+            "Some header\nfirst run\nsecond run\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn import_file() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let source = project.path().join("reference.txt");
+        fs::write(&source, "x y\n1 2\n").unwrap();
+
+        let mut file = FileManager::default()
+            .set_header("Ignored header")
+            .set_project_path(project.path().to_str().unwrap())
+            .set_output_path("reference/data")
+            .set_file_name("table")
+            .set_extension("dat")
             .build();
+        file.import_from(&source).unwrap();
+        assert!(file.writable());
+        assert_eq!(
+            &project.path().join("reference/data/table.dat"),
+            file.path()
+        );
 
-        let files = vec![
-            &mut test_file_1,
-            &mut test_file_2,
-            &mut test_file_3,
-            &mut test_file_4,
-        ];
+        file.append_line("3 4").unwrap();
+        assert_eq!("x y\n1 2\n3 4\n", fs::read_to_string(file.path()).unwrap());
+        assert_eq!("x y\n1 2\n", fs::read_to_string(&source).unwrap());
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Overwrite test: \
-                {reason}"
-            )
-        }
+        // A missing source leaves the file untouched
+        assert!(file.import_from(&project.path().join("missing")).is_err());
+        assert!(file.path().exists());
+    }
 
-        let files = vec![
-            &mut test_file_1,
-            &mut test_file_2,
-            &mut test_file_3,
-            &mut test_file_4,
-        ];
+    #[test]
+    fn files_from_config() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let config = Config::from_toml_str(
+            r#"
+            [[outputs]]
+            output_path = "energy"
+            name = "energy"
+            header = "time energy"
+
+            [[outputs]]
+            output_path = "snapshots"
+            name = "snapshot"
+            extension = "csv"
+            series = 2
+            "#,
+        );
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Overwrite test: \
-                {reason}"
-            )
-        }
+        let mut files =
+            FileManager::from_config_seq(&config, "outputs").unwrap();
+        assert!(files.iter().all(|file| !file.initialized()));
+        assert_eq!(Some(Series::new(2)), files[1].descriptor.series);
 
-        // Verify that correct files were initialized
-        assert!(
-            Path::new("./test_overwrite/dir_1/file_1.dat").exists(),
-            "file_1.dat was not created!"
+        project.initialize_output_files(files.iter_mut()).unwrap();
+        assert_eq!(
+            "time energy\n",
+            fs::read_to_string(project.path().join("energy/energy.dat"))
+                .unwrap()
         );
+        assert!(project.path().join("snapshots/snapshot_1.csv").exists());
+
+        // The serialized form (with the file index) is read back
+        let file: FileManager =
+            serde_json::from_str(&serde_json::to_string(&files[1]).unwrap())
+                .unwrap();
+        assert_eq!(Some(2), file.series_count());
+        assert_eq!(Some(1), file.series_index());
+    }
 
-        assert!(
-            Path::new("./test_overwrite/dir_1/file_2.csv").exists(),
-            "file_2.csv was not created!"
+    #[test]
+    fn series_start() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        // The pair of the number of files and the index is still accepted
+        let config = Config::from_toml_str(
+            r#"
+            [[outputs]]
+            output_path = ""
+            name = "pair"
+            series = [2, 1]
+
+            [[outputs]]
+            output_path = ""
+            name = "table"
+            series = { count = 2, start = 1 }
+            "#,
         );
 
-        assert!(
-            Path::new("./test_overwrite/dir_2/file_3.txt").exists(),
-            "file_3.txt was not created!"
+        let mut files =
+            FileManager::from_config_seq(&config, "outputs").unwrap();
+        assert_eq!(Some(1), files[0].series_index());
+        assert_eq!(
+            Some(1),
+            files[1].descriptor.series.map(|series| series.start())
         );
 
-        assert!(
-            Path::new("./test_overwrite/dir_3/file_4.dat").exists(),
-            "file_4.dat was not created!"
+        // A series without a start keeps the serialized form of the pair
+        assert_eq!(
+            "[2,1]",
+            serde_json::to_string(&files[0].descriptor.series.unwrap())
+                .unwrap()
+        );
+        let json = serde_json::to_string(&files[1].descriptor.series.unwrap())
+            .unwrap();
+        assert_eq!(r#"{"count":2,"index":0,"start":1}"#, json);
+        assert_eq!(
+            files[1].descriptor.series,
+            serde_json::from_str(&json).unwrap()
         );
 
-        // Verify final states of the FileManager
+        project.initialize_output_files(files.iter_mut()).unwrap();
+        assert!(project.path().join("pair_0.dat").exists());
+        assert!(project.path().join("table_1.dat").exists());
+        assert!(project.path().join("table_2.dat").exists());
+        assert!(!project.path().join("table_0.dat").exists());
+    }
+
+    #[test]
+    fn resolve_paths_only() {
+        let project = TempProject::new("dat", OverwriteType::None).unwrap();
+
+        let mut files = [
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file")
+                .build(),
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("series")
+                .set_series(2)
+                .build(),
+        ];
+        project.initialize_output_files(files.iter_mut()).unwrap();
+
+        assert_eq!(&project.path().join("dir/file.dat"), files[0].path());
+        assert_eq!(&project.path().join("dir/series_0.dat"), files[1].path());
+        assert!(!files[0].writable());
+        assert!(!project.path().join("dir").exists());
+    }
+
+    #[test]
+    fn created_directories() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        fs::create_dir_all(project.path().join("existing")).unwrap();
+
+        let mut file = FileManager::default()
+            .set_output_path("existing/new/nested")
+            .set_file_name("file")
+            .build();
+        project.initialize_output_files([&mut file]).unwrap();
+
+        let created: Vec<PathBuf> = file
+            .created_dirs()
+            .iter()
+            .map(|dir| dir.canonicalize().unwrap())
+            .collect();
+        let existing = project.path().join("existing").canonicalize().unwrap();
         assert_eq!(
-            PathBuf::from("./test_overwrite/dir_1/file_1.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_1.path(),
-            "The path of file_1.dat does not match the expected one."
+            vec![existing.join("new"), existing.join("new/nested")],
+            created
         );
 
+        // Directories with files are not removed
+        assert!(file.remove_created_dirs().is_err());
+
+        fs::remove_file(file.path()).unwrap();
+        file.remove_created_dirs().unwrap();
+        assert!(file.created_dirs().is_empty());
+        assert!(!existing.join("new").exists());
+        assert!(existing.exists());
+    }
+
+    #[test]
+    fn line_endings() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut files = [LineEnding::Crlf, LineEnding::Lf].map(|line_ending| {
+            FileManager::default()
+                .set_header("x y")
+                .set_output_path("dir")
+                .set_file_name(&format!("{line_ending:?}"))
+                .set_line_ending(line_ending)
+                .build()
+        });
+        project.initialize_output_files(files.iter_mut()).unwrap();
+
+        for file in &files {
+            file.append_line("1 2").unwrap();
+        }
+
         assert_eq!(
-            PathBuf::from("./test_overwrite/dir_1/file_2.csv")
-                .canonicalize()
-                .unwrap(),
-            *test_file_2.path(),
-            "The path of file_2.csv does not match the expected one."
+            b"x y\r\n1 2\r\n",
+            fs::read(files[0].path()).unwrap().as_slice()
+        );
+        assert_eq!(
+            b"x y\n1 2\n",
+            fs::read(files[1].path()).unwrap().as_slice()
         );
 
+        // The header is rewritten with the same ending
+        files[0].change_header("a b").unwrap();
         assert_eq!(
-            PathBuf::from("./test_overwrite/dir_2/file_3.txt")
-                .canonicalize()
-                .unwrap(),
-            *test_file_3.path(),
-            "The path of file_3.txt does not match the expected one."
+            b"a b\r\n1 2\r\n",
+            fs::read(files[0].path()).unwrap().as_slice()
         );
+    }
+
+    #[test]
+    fn writable_handle() {
+        let project = TempProject::new("dat", OverwriteType::Ignore).unwrap();
+
+        let new_file = || {
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file")
+                .build()
+        };
+
+        // Not initialized yet
+        let mut file = new_file();
+        assert!(matches!(file.try_writer(), Err(Error::NotWritable { .. })));
 
+        project.initialize_output_files([&mut file]).unwrap();
+        let writer = file.try_writer().unwrap();
+        writer.append_line("1 2 3").unwrap();
+        writeln!(writer.open_buffer().unwrap(), "4 5 6").unwrap();
         assert_eq!(
-            PathBuf::from("./test_overwrite/dir_3/file_4.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_4.path(),
-            "The path of file_4.dat does not match the expected one."
+            "1 2 3\n4 5 6\n",
+            fs::read_to_string(writer.file().path()).unwrap()
         );
 
-        // Delete test project directory tree
-        if let Err(reason) = remove_dir_all("./test_overwrite/") {
-            panic!(
-                "Cannot remove project directory ./test_overwrite/: {:?}",
-                reason
-            );
+        // The existing file is ignored
+        let mut ignored = new_file();
+        project.initialize_output_files([&mut ignored]).unwrap();
+        match ignored.try_writer() {
+            Err(Error::NotWritable { path }) => {
+                assert_eq!(ignored.path(), &path)
+            }
+            _ => panic!("Ignored file is writable"),
         }
     }
 
     #[test]
-    fn forbidden_overwrite() {
-        // Setup test project directory tree
-        let project_manager = ProjectManager {
-            path: "test_panic".to_owned(),
-            extension: "dat".to_owned(),
-            overwrite_type: OverwriteType::Panic,
+    fn buffer_with_capacity() {
+        let project = TempProject::new("dat", OverwriteType::Ignore).unwrap();
+
+        let new_file = || {
+            FileManager::default()
+                .set_output_path("dir")
+                .set_file_name("file")
+                .build()
         };
 
-        let mut test_file_1 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
-            .set_extension("dat")
-            .build();
+        let mut file = new_file();
+        project.initialize_output_files([&mut file]).unwrap();
+        {
+            let mut buffer = file.open_buffer_with_capacity(1 << 20);
+            assert_eq!(1 << 20, buffer.capacity());
+            for i in 0..3 {
+                writeln!(buffer, "{i} {}", i * i).unwrap();
+            }
+        }
+        let writer = file.try_writer().unwrap();
+        writeln!(writer.open_buffer_with_capacity(16).unwrap(), "3 9").unwrap();
+        assert_eq!(
+            "0 0\n1 1\n2 4\n3 9\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
 
-        let mut test_file_2 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_2")
-            .set_extension("csv")
-            .build();
+        // The ignored file cannot be written to
+        let mut ignored = new_file();
+        project.initialize_output_files([&mut ignored]).unwrap();
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ignored.open_buffer_with_capacity(1024);
+        }))
+        .is_err());
+    }
 
-        let mut test_file_3 = FileManager::default()
-            .set_output_path("dir_2")
-            .set_file_name("file_3")
-            .set_extension("dat")
-            .build();
+    #[test]
+    fn file_descriptor() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
 
-        let mut test_file_4 = FileManager::default()
-            .set_output_path("dir_3")
-            .set_file_name("file_4")
-            .set_extension("txt")
+        let mut file = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(2)
+            .set_header("x y")
             .build();
+        project.initialize_output_files([&mut file]).unwrap();
+
+        // The runtime state is not part of the descriptor
+        let descriptor = FileDescriptor::from(&file);
+        assert_eq!(Some(String::from("series")), descriptor.name);
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(serde_json::to_value(&descriptor).unwrap(), json);
+        assert!(json.get("path").is_none());
+        assert!(json.get("writable").is_none());
+
+        let read: FileManager = serde_json::from_value(json).unwrap();
+        assert!(!read.initialized());
+        assert!(read.same_config(&file));
+
+        // Distinct headers must cover the series
+        let descriptor = FileDescriptor {
+            series_headers: Some(vec![String::from("x")]),
+            ..descriptor
+        };
+        assert!(matches!(
+            FileManager::try_from(descriptor),
+            Err(Error::InvalidSeries { .. })
+        ));
+    }
 
-        let files = vec![
-            &mut test_file_1,
-            &mut test_file_2,
-            &mut test_file_3,
-            &mut test_file_4,
-        ];
-
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Panic test: \
-                {reason}"
-            )
+    #[test]
+    fn json_lines() {
+        let project =
+            TempProject::new("jsonl", OverwriteType::Overwrite).unwrap();
+
+        #[derive(Serialize)]
+        struct Event<'a> {
+            step: u32,
+            name: &'a str,
         }
 
-        let mut test_file_1_copy = FileManager::default()
-            .set_header("New file_1")
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
-            .set_extension("dat")
+        let mut file = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("events")
             .build();
 
-        let files = vec![&mut test_file_1_copy];
+        // Not writable before the initialization
+        let event = Event {
+            step: 0,
+            name: "start",
+        };
+        assert!(file.append_json(&event).is_err());
+
+        project.initialize_output_files([&mut file]).unwrap();
+        file.append_json(&event).unwrap();
+        file.append_json_batch(&[
+            Event {
+                step: 1,
+                name: "run",
+            },
+            Event {
+                step: 2,
+                name: "stop",
+            },
+        ])
+        .unwrap();
 
         assert_eq!(
-            Err(String::from(
-                "Permission denied to overwrite existing output files."
-            )),
-            project_manager.initialize_output_files(files)
+            "{\"step\":0,\"name\":\"start\"}\n\
+            {\"step\":1,\"name\":\"run\"}\n\
+            {\"step\":2,\"name\":\"stop\"}\n",
+            fs::read_to_string(file.path()).unwrap()
         );
+    }
 
-        // Delete test project directory tree
-        if let Err(reason) = remove_dir_all("./test_panic/") {
-            panic!(
-                "Cannot remove project directory ./test_panic/: {:?}",
-                reason
-            );
+    #[test]
+    fn scan_existing_series() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        for name in ["run_0.dat", "run_1.dat", "run_3.dat", "run_x.dat"] {
+            fs::write(project.path().join(name), "").unwrap();
         }
+        // Files of other series or extensions are not counted
+        for name in ["run_5.csv", "other_7.dat", "run_04.dat"] {
+            fs::write(project.path().join(name), "").unwrap();
+        }
+
+        let mut file = FileManager::default();
+        file.set_project_path(project.path().to_str().unwrap())
+            .set_output_path("")
+            .set_file_name("run")
+            .set_extension("dat");
+
+        // The gap (run_2.dat) is counted
+        assert_eq!(4, file.scan_series().unwrap());
+        let file = file.build();
+        assert_eq!(Some(4), file.series_count());
+        assert_eq!(&project.path().join("run_0.dat"), file.path());
+
+        // Nothing to scan in a new directory
+        let mut file = FileManager::default();
+        file.set_project_path(project.path().to_str().unwrap())
+            .set_output_path("new")
+            .set_file_name("run")
+            .set_extension("dat");
+        assert_eq!(0, file.scan_series().unwrap());
+
+        // Built managers are not rescanned
+        assert!(file.build().scan_series().is_err());
     }
 
     #[test]
-    fn archive_files() {
-        // Setup test project directory tree
-        let project_manager = ProjectManager {
-            path: "test_archive".to_owned(),
-            extension: "dat".to_owned(),
-            overwrite_type: OverwriteType::Archive,
-        };
+    fn byte_order_mark() {
+        let project =
+            TempProject::new("csv", OverwriteType::Overwrite).unwrap();
+
+        let mut with_bom = FileManager::default()
+            .set_output_path("")
+            .set_file_name("with_bom")
+            .set_header("x,y")
+            .set_write_bom(true)
+            .build();
+        let mut without_bom = FileManager::default()
+            .set_output_path("")
+            .set_file_name("without_bom")
+            .set_header("x,y")
+            .build();
+        project
+            .initialize_output_files([&mut with_bom, &mut without_bom])
+            .unwrap();
 
-        let mut test_file_1 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
-            .set_extension("dat")
+        for file in [&with_bom, &without_bom] {
+            file.append_line("1,2").unwrap();
+        }
+
+        // The mark is written once, before the header
+        let bytes = fs::read(with_bom.path()).unwrap();
+        assert_eq!(&UTF8_BOM, &bytes[..3]);
+        assert_eq!(b"x,y\n1,2\n", &bytes[3..]);
+        let bytes = fs::read(without_bom.path()).unwrap();
+        assert_eq!(b"x,y\n1,2\n", &bytes[..]);
+
+        // Rewriting the header keeps a single mark
+        with_bom.change_header("a,b").unwrap();
+        let bytes = fs::read(with_bom.path()).unwrap();
+        assert_eq!(&UTF8_BOM, &bytes[..3]);
+        assert_eq!(b"a,b\n1,2\n", &bytes[3..]);
+    }
+
+    #[test]
+    fn sync_on_append() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut file = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("checkpoint")
+            .set_sync_on_append(true)
             .build();
+        project.initialize_output_files([&mut file]).unwrap();
+
+        file.append_line("step 1").unwrap();
+        file.append_line("step 2").unwrap();
+        file.sync_all().unwrap();
+        assert_eq!(
+            "step 1\nstep 2\n",
+            fs::read_to_string(file.path()).unwrap()
+        );
+
+        // Standard streams are only flushed
+        FileManager::stdout().sync_all().unwrap();
+    }
+
+    #[test]
+    fn nested_output_path() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let mut test_files = [
+            FileManager::default()
+                .set_output_path("a/b/c")
+                .set_file_name("file_1")
+                .build(),
+            FileManager::default()
+                .set_output_path("a\\b\\c\\")
+                .set_file_name("file_2")
+                .build(),
+        ];
+        project.initialize_output_files(&mut test_files).unwrap();
+
+        let dir = project.path().join("a").join("b").join("c");
+        assert!(dir.is_dir());
+        assert_eq!(dir.join("file_1.dat"), *test_files[0].path());
+        assert_eq!(dir.join("file_2.dat"), *test_files[1].path());
+    }
+
+    #[test]
+    fn same_config() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        let new_file = |name: &str| {
+            FileManager::default()
+                .set_project_path(project.project().path())
+                .set_output_path("dir")
+                .set_file_name(name)
+                .set_extension("dat")
+                .set_series(2)
+                .clone()
+        };
 
-        let mut test_file_2 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_2")
-            .set_extension("csv")
-            .build();
+        let template = new_file("file");
+        let mut file = new_file("file");
+        project.initialize_output_files([&mut file]).unwrap();
 
-        let mut test_file_3 = FileManager::default()
-            .set_output_path("dir_2")
-            .set_file_name("file_3")
-            .set_extension("txt")
-            .build();
+        assert_ne!(template, file);
+        assert!(template.same_config(&file));
+        assert!(!template.same_config(&new_file("other")));
+    }
 
-        let mut test_file_4 = FileManager::default()
-            .set_output_path("dir_3")
-            .set_file_name("file_4")
+    #[test]
+    fn reset_template() {
+        let temp = TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+
+        // Template without a file name
+        let mut template = FileManager::default()
+            .set_header("x y")
+            .set_project_path(temp.path().to_str().unwrap())
+            .set_output_path("dir")
+            .set_extension("dat")
+            .set_canonicalize(false)
             .build();
 
-        let files = vec![
-            &mut test_file_1,
-            &mut test_file_2,
-            &mut test_file_3,
-            &mut test_file_4,
-        ];
+        let files: Vec<FileManager> = (0..3)
+            .map(|i| {
+                template
+                    .clone()
+                    .reset()
+                    .set_file_name(&format!("file_{i}"))
+                    .build()
+            })
+            .collect();
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Archive test: \
-                {reason}"
-            )
+        for (i, file) in files.iter().enumerate() {
+            assert_eq!(
+                temp.path().join("dir").join(format!("file_{i}.dat")),
+                *file.path()
+            );
+            assert_eq!(Some("x y"), file.header());
         }
 
-        let mut test_file_1_copy = FileManager::default()
-            .set_header("New file_1")
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
-            .build();
+        // Previously set fields are not replaced by the Setters
+        template.reset().set_header("other");
+        assert_eq!(Some("x y"), template.header());
+        assert!(!template.initialized());
+    }
 
-        let files = vec![&mut test_file_1_copy];
+    #[test]
+    fn keep_logical_path() {
+        let mut file = FileManager::default()
+            .set_project_path("test_no_canonicalize")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_canonicalize(false)
+            .build();
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Archive test: \
-                {reason}"
-            )
-        }
+        file.initialize_output();
 
-        // Verify that correct files were initialized
-        assert!(
-            Path::new("./test_archive/dir_1/file_1.dat").exists(),
-            "file_1.dat was not created!"
+        assert!(!file.canonicalize());
+        assert!(file.path().exists());
+        assert_eq!(
+            PathBuf::from("test_no_canonicalize/dir/file.dat"),
+            *file.path()
         );
 
-        assert!(
-            Path::new("./test_archive/dir_1/file_2.csv").exists(),
-            "file_2.csv was not created!"
+        // The path is recalculated without canonicalization as well
+        file.change_file_name("other");
+        assert_eq!(
+            PathBuf::from("test_no_canonicalize/dir/other.dat"),
+            *file.path()
         );
 
-        assert!(
-            Path::new("./test_archive/dir_2/file_3.txt").exists(),
-            "file_3.txt was not created!"
-        );
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_no_canonicalize/") {
+            panic!(
+                "Cannot remove project directory ./test_no_canonicalize/: {:?}",
+                reason
+            );
+        }
+    }
 
-        assert!(
-            Path::new("./test_archive/dir_3/file_4.dat").exists(),
-            "file_4.dat was not created!"
-        );
+    #[test]
+    fn sanitize_names() {
+        assert_eq!("a_b_c", sanitize_file_name("a:b*c"));
+        assert_eq!("data__", sanitize_file_name("data. "));
+        assert_eq!("CON_", sanitize_file_name("CON"));
+        assert_eq!("nul_", sanitize_file_name("nul"));
+        assert_eq!("console", sanitize_file_name("console"));
+
+        // Reserved characters are replaced
+        let file = FileManager::default()
+            .set_project_path(".")
+            .set_output_path("test_sanitize")
+            .set_file_name("t=0:5")
+            .set_extension("dat")
+            .set_sanitize_names(true)
+            .try_build()
+            .unwrap();
+        assert_eq!(PathBuf::from("./test_sanitize/t=0_5.dat"), *file.path());
 
-        assert!(
-            Path::new("./test_archive/archive/dir_1/file_1.dat").exists(),
-            "file_1.dat was not created!"
-        );
+        // Strict mode refuses to change the name
+        let result = FileManager::default()
+            .set_project_path(".")
+            .set_output_path("test_sanitize")
+            .set_file_name("t=0:5")
+            .set_extension("dat")
+            .set_sanitize_names(true)
+            .set_strict_names(true)
+            .try_build();
+        assert!(matches!(result, Err(Error::InvalidName { .. })));
+
+        // Reserved device names are rejected
+        let mut file = FileManager::default();
+        file.set_project_path(".")
+            .set_output_path("test_sanitize")
+            .set_file_name("aux")
+            .set_extension("dat")
+            .set_sanitize_names(true);
+        assert!(matches!(file.try_build(), Err(Error::InvalidName { .. })));
 
-        // Verify final states of the FileManager
+        // build still produces a usable path
         assert_eq!(
-            PathBuf::from("./test_archive/dir_1/file_1.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_1.path(),
-            "The path of file_1.dat does not match the expected one."
+            PathBuf::from("./test_sanitize/aux_.dat"),
+            *file.build().path()
         );
 
-        assert_eq!(
-            PathBuf::from("./test_archive/dir_1/file_2.csv")
-                .canonicalize()
-                .unwrap(),
-            *test_file_2.path(),
-            "The path of file_2.csv does not match the expected one."
-        );
+        // No sanitization when disabled
+        let file = FileManager::default()
+            .set_project_path(".")
+            .set_output_path("test_sanitize")
+            .set_file_name("t=0:5")
+            .set_extension("dat")
+            .set_sanitize_names(false)
+            .set_strict_names(true)
+            .try_build()
+            .unwrap();
+        assert_eq!(PathBuf::from("./test_sanitize/t=0:5.dat"), *file.path());
+    }
+
+    #[test]
+    fn rotate_files() {
+        let mut file = FileManager::default()
+            .set_header("header")
+            .set_project_path("test_rotate")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .set_extension("dat")
+            .set_max_size(20)
+            .build();
 
+        file.initialize_output();
+
+        // Each line is 11 bytes long, and the header is 7 bytes long
+        for _ in 0..5 {
+            file.append_line("0123456789").unwrap();
+        }
+
+        let full = "header\n0123456789\n0123456789\n";
         assert_eq!(
-            PathBuf::from("./test_archive/dir_2/file_3.txt")
-                .canonicalize()
-                .unwrap(),
-            *test_file_3.path(),
-            "The path of file_3.txt does not match the expected one."
+            full,
+            fs::read_to_string("./test_rotate/dir/file.1.dat").unwrap()
         );
-
         assert_eq!(
-            PathBuf::from("./test_archive/dir_3/file_4.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_4.path(),
-            "The path of file_4.dat does not match the expected one."
+            full,
+            fs::read_to_string("./test_rotate/dir/file.2.dat").unwrap()
         );
-
         assert_eq!(
-            PathBuf::from("./test_archive/dir_1/file_1.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_1_copy.path(),
-            "The path of file_1.dat does not match the expected one."
+            "header\n0123456789\n",
+            fs::read_to_string("./test_rotate/dir/file.dat").unwrap()
         );
+        assert!(!Path::new("./test_rotate/dir/file.3.dat").exists());
 
         // Delete test project directory tree
-        if let Err(reason) = remove_dir_all("./test_archive/") {
+        if let Err(reason) = remove_dir_all("./test_rotate/") {
             panic!(
-                "Cannot remove project directory ./test_archive/: {:?}",
+                "Cannot remove project directory ./test_rotate/: {:?}",
                 reason
             );
         }
     }
 
     #[test]
-    fn ignore_files() {
-        // Setup test project directory tree
-        let project_manager = ProjectManager {
-            path: "test_ignore".to_owned(),
-            extension: "dat".to_owned(),
-            overwrite_type: OverwriteType::Ignore,
-        };
-
-        let mut test_file_1 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
+    fn change_header() {
+        let mut file = FileManager::default()
+            .set_header("a b")
+            .set_project_path("test_change_header")
+            .set_output_path("dir")
+            .set_file_name("file")
             .set_extension("dat")
             .build();
 
-        let mut test_file_2 = FileManager::default()
-            .set_output_path("dir_1")
-            .set_file_name("file_2")
-            .set_extension("csv")
-            .build();
+        assert_eq!(Some("a b"), file.header());
 
-        let mut test_file_3 = FileManager::default()
-            .set_output_path("dir_2")
-            .set_file_name("file_3")
-            .set_extension("txt")
-            .build();
+        file.initialize_output();
+        file.append_line("1 2").unwrap();
 
-        let mut test_file_4 = FileManager::default()
-            .set_output_path("dir_3")
-            .set_file_name("file_4")
+        // Replace the existing header
+        file.change_header("x y").unwrap();
+        assert_eq!(Some("x y"), file.header());
+        assert_eq!("x y\n1 2\n", fs::read_to_string(file.path()).unwrap());
+
+        // Insert a header into a file without one
+        let mut file = FileManager::default()
+            .set_project_path("test_change_header")
+            .set_output_path("dir")
+            .set_file_name("no_header")
+            .set_extension("dat")
             .build();
 
-        let files = vec![
-            &mut test_file_1,
-            &mut test_file_2,
-            &mut test_file_3,
-            &mut test_file_4,
-        ];
+        file.initialize_output();
+        file.append_line("1 2").unwrap();
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
-            panic!(
-                "Could not initialize output files for Ignore test: \
-                {reason}"
-            )
-        }
+        file.change_header("x y").unwrap();
+        assert_eq!("x y\n1 2\n", fs::read_to_string(file.path()).unwrap());
 
-        let mut test_file_1_copy = FileManager::default()
-            .set_header("New file_1")
-            .set_output_path("dir_1")
-            .set_file_name("file_1")
+        // Write a header into an empty file
+        let mut file = FileManager::default()
+            .set_project_path("test_change_header")
+            .set_output_path("dir")
+            .set_file_name("empty")
             .set_extension("dat")
             .build();
 
-        let files = vec![&mut test_file_1_copy];
+        file.initialize_output();
 
-        if let Err(reason) = project_manager.initialize_output_files(files) {
+        file.change_header("x y").unwrap();
+        assert_eq!("x y\n", fs::read_to_string(file.path()).unwrap());
+
+        // Delete test project directory tree
+        if let Err(reason) = remove_dir_all("./test_change_header/") {
             panic!(
-                "Could not initialize output files for Ignore test: \
-                {reason}"
-            )
+                "Cannot remove project directory ./test_change_header/: {:?}",
+                reason
+            );
         }
+    }
 
-        // Verify that correct files were initialized
-        assert!(
-            Path::new("./test_ignore/dir_1/file_1.dat").exists(),
-            "file_1.dat was not created!"
-        );
-
-        assert!(
-            Path::new("./test_ignore/dir_1/file_2.csv").exists(),
-            "file_2.csv was not created!"
-        );
+    #[test]
+    fn temp_file() {
+        let file = FileManager::temp("dat").unwrap();
+        let path = file.path().clone();
+
+        assert!(path.exists());
+        assert!(path.starts_with(std::env::temp_dir().canonicalize().unwrap()));
+        assert_eq!(Some("dat".as_ref()), path.extension());
+
+        file.append_line("1 2 3").unwrap();
+        assert_eq!("1 2 3\n", fs::read_to_string(&path).unwrap());
+
+        // Names are unique
+        let other = FileManager::temp("dat").unwrap();
+        assert_ne!(path, *other.path());
+
+        // The file is kept on drop by default
+        let other_path = other.path().clone();
+        drop(other);
+        assert!(other_path.exists());
+        fs::remove_file(other_path).unwrap();
+
+        // Dropping a clone keeps the file of the opted-in original
+        let mut file = file;
+        file.change_cleanup_on_drop(true);
+        drop(file.clone());
+        assert!(path.exists());
+
+        drop(file);
+        assert!(!path.exists());
+    }
 
-        assert!(
-            Path::new("./test_ignore/dir_2/file_3.txt").exists(),
-            "file_3.txt was not created!"
-        );
+    #[test]
+    fn from_full_path() {
+        let file = FileManager::from_full_path(Path::new(
+            "/data/project/dir/file.dat",
+        ))
+        .unwrap();
 
-        assert!(
-            Path::new("./test_ignore/dir_3/file_4.dat").exists(),
-            "file_4.dat was not created!"
+        assert_eq!(
+            Some(String::from("/data/project")),
+            file.descriptor.project_path
         );
+        assert_eq!(Some(String::from("dir")), file.descriptor.output_path);
+        assert_eq!(Some(String::from("file")), file.descriptor.name);
+        assert_eq!(Some(String::from("dat")), file.descriptor.extension);
+        assert_eq!(PathBuf::from("/data/project/dir/file.dat"), *file.path());
+
+        // Relative paths without a project directory
+        let file =
+            FileManager::from_full_path(Path::new("dir/file.dat")).unwrap();
+        assert_eq!(Some(String::from(".")), file.descriptor.project_path);
+        assert_eq!(Some(String::from("dir")), file.descriptor.output_path);
+
+        // Paths with too few components
+        for path in ["file.dat", "/file.dat", "dir/file", "/"] {
+            assert!(
+                matches!(
+                    FileManager::from_full_path(Path::new(path)),
+                    Err(Error::InvalidPath { .. })
+                ),
+                "{path} should be rejected"
+            );
+        }
+    }
 
-        // Verify final states of the FileManager
-        assert_eq!(
-            PathBuf::from("./test_ignore/dir_1/file_1.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_1.path(),
-            "The path of file_1.dat does not match the expected one."
+    #[test]
+    fn write_manifest() {
+        // Setup test project directory tree
+        let project_manager = ProjectManager::new(
+            "test_manifest".to_owned(),
+            "dat".to_owned(),
+            OverwriteType::Overwrite,
         );
 
-        assert!(!test_file_1_copy.writable(), "{:?}", test_file_1_copy);
+        let mut test_file = FileManager::default()
+            .set_header("Some header")
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
 
-        assert_eq!(
-            PathBuf::from("./test_ignore/dir_1/file_2.csv")
-                .canonicalize()
-                .unwrap(),
-            *test_file_2.path(),
-            "The path of file_2.csv does not match the expected one."
-        );
+        let mut test_series = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("series")
+            .set_series(3)
+            .build();
 
-        assert_eq!(
-            PathBuf::from("./test_ignore/dir_2/file_3.txt")
-                .canonicalize()
-                .unwrap(),
-            *test_file_3.path(),
-            "The path of file_3.txt does not match the expected one."
-        );
+        if let Err(reason) = project_manager
+            .initialize_output_files(vec![&mut test_file, &mut test_series])
+        {
+            panic!(
+                "Could not initialize output files for manifest test: \
+                {reason}"
+            )
+        }
 
-        assert_eq!(
-            PathBuf::from("./test_ignore/dir_3/file_4.dat")
-                .canonicalize()
-                .unwrap(),
-            *test_file_4.path(),
-            "The path of file_4.dat does not match the expected one."
-        );
+        let manifest_path = Path::new("./test_manifest/manifest.json");
+        project_manager
+            .write_manifest(&[&test_file, &test_series], manifest_path)
+            .unwrap();
+
+        // Read the manifest back and locate all outputs
+        let manifest = Manifest::load(manifest_path).unwrap();
+
+        assert_eq!("test_manifest", manifest.project_path());
+        assert_eq!(2, manifest.entries().len());
+
+        let file_entry = &manifest.entries()[0];
+        assert_eq!(Some("Some header"), file_entry.header());
+        assert_eq!(None, file_entry.series());
+        assert_eq!(1, file_entry.files().len());
+        assert_eq!(test_file.path(), file_entry.files()[0].path());
+        assert_eq!(12, file_entry.files()[0].size());
+
+        let series_entry = &manifest.entries()[1];
+        assert_eq!(Some(3), series_entry.series());
+        assert_eq!(3, series_entry.files().len());
+        for (i, file) in series_entry.files().iter().enumerate() {
+            assert!(file.path().exists());
+            assert!(file.path().ends_with(format!("dir/series_{i}.dat")));
+            assert_eq!(0, file.size());
+        }
 
         // Delete test project directory tree
-        if let Err(reason) = remove_dir_all("./test_ignore/") {
+        if let Err(reason) = remove_dir_all("./test_manifest/") {
             panic!(
-                "Cannot remove project directory ./test_ignore/: {:?}",
+                "Cannot remove project directory ./test_manifest/: {:?}",
                 reason
             );
         }
     }
 
-    #[test]
-    fn define_builder() {
-        let file = FileManager::default()
-            .set_header("Some header")
-            .set_project_path(".")
-            .set_output_path("test")
-            .set_file_name("test")
-            .set_extension("dat")
-            .build();
-
-        assert_eq!(
-            FileManager {
-                header: Some(String::from("Some header")),
-                project_path: Some(String::from(".")),
-                output_path: Some(String::from("test")),
-                name: Some(String::from("test")),
-                extension: Some(String::from("dat")),
-                series: None,
-                path: Some(PathBuf::from("./test/test.dat")),
-                writable: false
-            },
-            file
-        );
-    }
-
     #[test]
     fn file_series() {
         // Setup test project directory tree
-        let project_manager = ProjectManager {
-            path: "test_series".to_owned(),
-            extension: "dat".to_owned(),
-            overwrite_type: OverwriteType::Overwrite,
-        };
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let project_manager = project.project();
 
         let mut test_file = FileManager::default()
             .set_output_path("dir")
@@ -1039,17 +7604,9 @@ mod tests {
         for i in 0..10 {
             // Verify that correct files were initialized
             assert!(
-                Path::new(&format!("./test_series/dir/file_{i}.dat")).exists(),
+                project.path().join(format!("dir/file_{i}.dat")).exists(),
                 "file_{i}.dat was not created!"
             );
         }
-
-        // Delete test project directory tree
-        if let Err(reason) = remove_dir_all("./test_series/") {
-            panic!(
-                "Cannot remove project directory ./test_series/: {:?}",
-                reason
-            );
-        }
     }
 }
@@ -1,6 +1,17 @@
 // Copyright Andrey Zelenskiy, 2024
+use std::any::type_name;
+
+use std::fmt;
+
+use std::ops::{Bound, RangeBounds};
+
 use crate::config_parse::{Config, FromConfig};
 
+use crate::error::Error;
+
+#[cfg(feature = "derive")]
+pub use io_utils_derive::Builder;
+
 /* ------------------------------------ */
 /* Methods for structure initialization */
 /* ------------------------------------ */
@@ -32,6 +43,118 @@ pub trait TargetFromBuilder {
         //Populate the parameters from the config
         Self::Builder::from_config(config, config_name).build()
     }
+
+    // Name of the config table for the Target, defaults to the name of the
+    // type in snake_case (e.g. TargetStruct -> target_struct)
+    fn default_table_name() -> String {
+        let name = type_name::<Self>();
+
+        // Strip the module path and the generic parameters
+        let name = name.split('<').next().unwrap_or(name);
+        let name = name.rsplit("::").next().unwrap_or(name);
+
+        to_snake_case(name)
+    }
+
+    // Initialize Target from the default table of a config file
+    fn from_config_default(config: &Config) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_config(config, &Self::default_table_name())
+    }
+}
+
+/* ------------------------------ */
+/* Validation of input parameters */
+/* ------------------------------ */
+
+// Check that the value of the field lies in the range, e.g. 0.0..=1.0 or
+// (Bound::Excluded(0.0), Bound::Included(1.0)) for 0 < value <= 1. Returns
+// the value, so that it can be used directly in BuilderMethods::build
+pub fn validate_range<T: PartialOrd + fmt::Display>(
+    field: &str,
+    value: T,
+    range: impl RangeBounds<T>,
+) -> Result<T, Error> {
+    if range.contains(&value) {
+        return Ok(value);
+    }
+
+    let start = match range.start_bound() {
+        Bound::Included(start) => format!("[{start}"),
+        Bound::Excluded(start) => format!("({start}"),
+        Bound::Unbounded => String::from("(-inf"),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => format!("{end}]"),
+        Bound::Excluded(end) => format!("{end})"),
+        Bound::Unbounded => String::from("inf)"),
+    };
+
+    Err(Error::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+        reason: format!("must be in {start}, {end}"),
+    })
+}
+
+// Check that the value of the field is strictly positive
+pub fn validate_positive<T: PartialOrd + Default + fmt::Display>(
+    field: &str,
+    value: T,
+) -> Result<T, Error> {
+    if value > T::default() {
+        Ok(value)
+    } else {
+        Err(Error::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: String::from("must be positive"),
+        })
+    }
+}
+
+// Check that the value of the field is zero or positive
+pub fn validate_non_negative<T: PartialOrd + Default + fmt::Display>(
+    field: &str,
+    value: T,
+) -> Result<T, Error> {
+    if value >= T::default() {
+        Ok(value)
+    } else {
+        Err(Error::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: String::from("must not be negative"),
+        })
+    }
+}
+
+// Convert a CamelCase type name to snake_case
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake_case = String::with_capacity(name.len() + 4);
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower =
+                chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            // Start a new word after a lowercase letter or a digit, or at the
+            // end of an acronym (e.g. HTTPServer -> http_server)
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                snake_case.push('_');
+            }
+        }
+        snake_case.extend(c.to_lowercase());
+    }
+
+    snake_case
 }
 
 #[cfg(test)]
@@ -92,4 +215,167 @@ mod tests {
         assert_eq!(2, target.xy);
         assert_eq!(4, target.y2);
     }
+
+    #[test]
+    fn build_from_default_table() {
+        assert_eq!("target_struct", TargetStruct::default_table_name());
+
+        let config = Config::from_toml_str(
+            r#"
+            [target_struct]
+            x = 2
+            y = 3
+            "#,
+        );
+        let target = TargetStruct::from_config_default(&config);
+
+        assert_eq!(4, target.x2);
+        assert_eq!(6, target.xy);
+        assert_eq!(9, target.y2);
+    }
+
+    #[cfg(feature = "derive")]
+    mod derive_tests {
+        use super::*;
+
+        #[derive(Builder, Debug, PartialEq)]
+        pub struct DerivedStruct {
+            x: u32,
+            #[builder(default = 10)]
+            y: u32,
+            #[builder(default)]
+            name: String,
+        }
+
+        #[test]
+        fn build_derived() {
+            let target = DerivedStruct::builder().set_x(1).build();
+            assert_eq!(
+                DerivedStruct {
+                    x: 1,
+                    y: 10,
+                    name: String::new()
+                },
+                target
+            );
+
+            let target = DerivedStruct::builder()
+                .set_x(2)
+                .set_y(3)
+                .set_name("derived".to_string())
+                .build();
+            assert_eq!(
+                DerivedStruct {
+                    x: 2,
+                    y: 3,
+                    name: String::from("derived")
+                },
+                target
+            );
+        }
+
+        #[test]
+        fn derived_from_config() {
+            let config = Config::from_toml_str(
+                r#"
+                [derived_struct]
+                x = 4
+                "#,
+            );
+            let target = DerivedStruct::from_config_default(&config);
+
+            assert_eq!(
+                DerivedStruct {
+                    x: 4,
+                    y: 10,
+                    name: String::new()
+                },
+                target
+            );
+        }
+    }
+
+    // Target validating its parameters while building
+    #[derive(Debug)]
+    pub struct Sampler {
+        probability: f64,
+        samples: u32,
+    }
+
+    #[derive(Deserialize, Default)]
+    pub struct SamplerBuilder {
+        probability: f64,
+        samples: u32,
+    }
+
+    impl BuilderMethods for SamplerBuilder {
+        type Target = Sampler;
+
+        fn build(&mut self) -> Self::Target {
+            let validated = || -> Result<Sampler, Error> {
+                Ok(Sampler {
+                    probability: validate_range(
+                        "probability",
+                        self.probability,
+                        (Bound::Excluded(0.0), Bound::Included(1.0)),
+                    )?,
+                    samples: validate_positive("samples", self.samples)?,
+                })
+            };
+            validated().unwrap_or_else(|e| panic!("{e}"))
+        }
+    }
+
+    impl TargetFromBuilder for Sampler {
+        type Builder = SamplerBuilder;
+    }
+
+    #[test]
+    fn validate_parameters() {
+        let config = Config::from_toml_str(
+            "[sampler]\nprobability = 0.5\nsamples = 10\n",
+        );
+        let sampler = Sampler::from_config_default(&config);
+        assert_eq!(0.5, sampler.probability);
+        assert_eq!(10, sampler.samples);
+
+        let config = Config::from_toml_str(
+            "[sampler]\nprobability = 0.0\nsamples = 5\n",
+        );
+        let result = std::panic::catch_unwind(|| {
+            Sampler::from_config_default(&config);
+        });
+        assert!(result.is_err());
+
+        let error = validate_range("probability", 1.5, 0.0..=1.0).unwrap_err();
+        assert_eq!(
+            "Invalid value 1.5 of probability: must be in [0, 1]",
+            error.to_string()
+        );
+        let error = validate_range("steps", 10, ..10).unwrap_err();
+        assert_eq!(
+            "Invalid value 10 of steps: must be in (-inf, 10)",
+            error.to_string()
+        );
+        assert_eq!(3, validate_range("steps", 3, 1..).unwrap());
+
+        let error = validate_positive("samples", 0).unwrap_err();
+        assert_eq!(
+            "Invalid value 0 of samples: must be positive",
+            error.to_string()
+        );
+        assert_eq!(0.0, validate_non_negative("rate", 0.0).unwrap());
+        assert!(matches!(
+            validate_non_negative("rate", -0.1),
+            Err(Error::InvalidValue { field, .. }) if field == "rate"
+        ));
+    }
+
+    #[test]
+    fn snake_case_names() {
+        assert_eq!("target", to_snake_case("Target"));
+        assert_eq!("target_struct", to_snake_case("TargetStruct"));
+        assert_eq!("http_server", to_snake_case("HTTPServer"));
+        assert_eq!("vec3_field", to_snake_case("Vec3Field"));
+    }
 }
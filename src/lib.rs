@@ -1,4 +1,21 @@
 // Copyright Andrey Zelenskiy, 2024
+pub mod compression;
 pub mod config_parse;
+pub mod error;
 pub mod files;
 pub mod initialize;
+#[cfg(feature = "time")]
+pub mod serde_helpers;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+// Allows the derive macros to refer to the crate as io_utils internally
+#[cfg(feature = "derive")]
+extern crate self as io_utils;
+
+// Dependencies used by the code generated with the derive macros
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+}
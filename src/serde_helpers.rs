@@ -0,0 +1,252 @@
+// Copyright Andrey Zelenskiy, 2025
+
+//! Helpers for `#[serde(with = "...")]` on the fields of config targets
+
+/// Human-friendly durations such as "30s", "250ms" or "1h30m" (a numeric
+/// value is read as seconds)
+pub mod duration_str {
+    use std::time::Duration;
+
+    use serde::de::{self, Visitor};
+
+    use serde::{Deserializer, Serializer};
+
+    use std::fmt;
+
+    // Units from the shortest to the longest
+    const UNITS: [(&str, Duration); 7] = [
+        ("ns", Duration::from_nanos(1)),
+        ("us", Duration::from_micros(1)),
+        ("ms", Duration::from_millis(1)),
+        ("s", Duration::from_secs(1)),
+        ("m", Duration::from_secs(60)),
+        ("h", Duration::from_secs(3600)),
+        ("d", Duration::from_secs(86400)),
+    ];
+
+    /// Parses a duration made of <number><unit> parts, e.g. "1h30m" or
+    /// "1.5s", with the units ns, us, ms, s, m, h and d
+    pub fn parse(value: &str) -> Result<Duration, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(String::from("empty duration"));
+        }
+
+        let mut total = Duration::ZERO;
+        let mut rest = value;
+
+        while !rest.is_empty() {
+            let number_len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let (number, tail) = rest.split_at(number_len);
+
+            let number: f64 = number
+                .parse()
+                .map_err(|_| format!("invalid duration {value:?}"))?;
+
+            let unit_len = tail
+                .find(|c: char| c.is_ascii_digit() || c == '.')
+                .unwrap_or(tail.len());
+            let (unit, tail) = tail.split_at(unit_len);
+
+            let scale = match UNITS.iter().find(|(name, _)| *name == unit) {
+                Some((_, scale)) => *scale,
+                None => {
+                    return Err(format!(
+                        "unknown unit {unit:?} in duration {value:?}"
+                    ))
+                }
+            };
+
+            total = Duration::try_from_secs_f64(scale.as_secs_f64() * number)
+                .ok()
+                .and_then(|part| total.checked_add(part))
+                .ok_or_else(|| format!("duration {value:?} is too large"))?;
+            rest = tail;
+        }
+
+        Ok(total)
+    }
+
+    /// Formats a duration in the largest unit representing it exactly
+    pub fn format(duration: &Duration) -> String {
+        let nanos = duration.as_nanos();
+
+        for (name, scale) in UNITS.iter().rev() {
+            let scale = scale.as_nanos();
+            if nanos.is_multiple_of(scale) {
+                return format!("{}{name}", nanos / scale);
+            }
+        }
+
+        // Unreachable as every duration is a whole number of nanoseconds
+        format!("{nanos}ns")
+    }
+
+    pub fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        deserializer.deserialize_any(DurationVisitor)
+    }
+
+    struct DurationVisitor;
+
+    impl Visitor<'_> for DurationVisitor {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a duration such as \"30s\" or a number of seconds")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+            parse(value).map_err(E::custom)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Duration, E> {
+            Ok(Duration::from_secs(value))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Duration, E> {
+            match u64::try_from(value) {
+                Ok(value) => Ok(Duration::from_secs(value)),
+                Err(_) => Err(E::custom("negative duration")),
+            }
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Duration, E> {
+            Duration::try_from_secs_f64(value).map_err(E::custom)
+        }
+    }
+}
+
+/// Datetimes written either as TOML datetimes (start = 2024-01-01T00:00:00Z)
+/// or as RFC 3339 strings (e.g. in JSON), deserialized into
+/// `chrono::DateTime<Utc>` and serialized as RFC 3339 strings
+pub mod toml_datetime {
+    use chrono::{DateTime, Utc};
+
+    use serde::de::Error;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    // Either representation of the datetime in a config
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDatetime {
+        Toml(toml::value::Datetime),
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(
+        datetime: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&datetime.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let raw = match RawDatetime::deserialize(deserializer)? {
+            RawDatetime::Toml(datetime) => datetime.to_string(),
+            RawDatetime::String(datetime) => datetime,
+        };
+
+        match DateTime::parse_from_rfc3339(&raw) {
+            Ok(datetime) => Ok(datetime.with_timezone(&Utc)),
+            Err(reason) => Err(D::Error::custom(format!(
+                "invalid datetime {raw:?} (expected RFC 3339 with an \
+                offset): {reason}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::duration_str;
+
+    use crate::config_parse::{Config, FromConfig};
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use serde::{Deserialize, Serialize};
+
+    use std::time::Duration;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Schedule {
+        #[serde(with = "super::duration_str")]
+        timeout: Duration,
+        #[serde(with = "super::toml_datetime")]
+        start: DateTime<Utc>,
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Ok(Duration::from_secs(30)), duration_str::parse("30s"));
+        assert_eq!(
+            Ok(Duration::from_millis(250)),
+            duration_str::parse("250ms")
+        );
+        assert_eq!(Ok(Duration::from_secs(5400)), duration_str::parse("1h30m"));
+        assert_eq!(
+            Ok(Duration::from_millis(1500)),
+            duration_str::parse("1.5s")
+        );
+        assert!(duration_str::parse("30").is_err());
+        assert!(duration_str::parse("30 parsecs").is_err());
+        assert!(duration_str::parse("").is_err());
+
+        // Overflowing durations are refused instead of panicking
+        let nines = format!("{}s", "9".repeat(30));
+        assert!(duration_str::parse(&nines).is_err());
+        assert!(duration_str::parse("18446744073709551615s1s").is_err());
+
+        assert_eq!("90m", duration_str::format(&Duration::from_secs(5400)));
+        assert_eq!(
+            "1500ms",
+            duration_str::format(&Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn schedule_from_config() {
+        let expected = Schedule {
+            timeout: Duration::from_secs(30),
+            start: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        };
+
+        let config = Config::from_toml_str(
+            "[schedule]\ntimeout = \"30s\"\nstart = 2024-01-01T00:00:00Z\n",
+        );
+        assert_eq!(expected, Schedule::from_config(&config, "schedule"));
+
+        let config = Config::from_json_str(
+            r#"{"schedule": {"timeout": 30, "start": "2024-01-01T01:00:00+01:00"}}"#,
+        );
+        assert_eq!(expected, Schedule::from_config(&config, "schedule"));
+
+        // Round trip through a string representation
+        let json = serde_json::to_string(&expected).unwrap();
+        assert_eq!(
+            r#"{"timeout":"30s","start":"2024-01-01T00:00:00+00:00"}"#,
+            json
+        );
+        assert_eq!(expected, serde_json::from_str(&json).unwrap());
+
+        // Local datetimes without an offset are ambiguous
+        let config = Config::from_toml_str(
+            "[schedule]\ntimeout = \"30s\"\nstart = 2024-01-01T00:00:00\n",
+        );
+        assert!(Schedule::try_from_config(&config, "schedule").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright Andrey Zelenskiy, 2025
+use std::fs;
+
+use std::io;
+
+use std::ops::Deref;
+
+use std::path::{Path, PathBuf};
+
+use crate::files::{unique_name, OverwriteType, ProjectManager};
+
+/// Project rooted at a unique temporary directory, the whole directory tree
+/// is removed when the guard is dropped (including during a panic)
+pub struct TempProject {
+    // Project manager rooted at the temporary directory
+    project: ProjectManager,
+    // Absolute path of the temporary directory
+    path: PathBuf,
+}
+
+impl TempProject {
+    /// Creates a new temporary project directory
+    pub fn new(
+        extension: &str,
+        overwrite_type: OverwriteType,
+    ) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(unique_name("io_utils_project"));
+        fs::create_dir_all(&path)?;
+        let path = path.canonicalize()?;
+
+        let project_path = match path.to_str() {
+            Some(project_path) => project_path.to_string(),
+            None => {
+                // Don't leak the directory
+                fs::remove_dir_all(&path)?;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Temp directory {path:?} is not valid unicode"),
+                ));
+            }
+        };
+
+        Ok(Self {
+            project: ProjectManager::new(
+                project_path,
+                extension.to_string(),
+                overwrite_type,
+            ),
+            path,
+        })
+    }
+
+    /// Returns the inner project manager
+    pub fn project(&self) -> &ProjectManager {
+        &self.project
+    }
+
+    /// Returns the inner project manager as mutable
+    pub fn project_mut(&mut self) -> &mut ProjectManager {
+        &mut self.project
+    }
+
+    /// Returns the path to the temporary project directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Deref for TempProject {
+    type Target = ProjectManager;
+
+    fn deref(&self) -> &Self::Target {
+        &self.project
+    }
+}
+
+impl Drop for TempProject {
+    fn drop(&mut self) {
+        // Nothing can be done about a failure at this point
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::AssertUnwindSafe;
+
+    use crate::files::FileManager;
+
+    #[test]
+    fn remove_on_drop() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let path = project.path().to_path_buf();
+
+        let mut file = FileManager::default()
+            .set_output_path("dir")
+            .set_file_name("file")
+            .build();
+        project.initialize_output_files(vec![&mut file]).unwrap();
+
+        assert!(path.join("dir/file.dat").exists());
+        assert_eq!(project.path().to_str().unwrap(), project.project().path());
+
+        drop(project);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn remove_on_panic() {
+        let project =
+            TempProject::new("dat", OverwriteType::Overwrite).unwrap();
+        let path = project.path().to_path_buf();
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(move || {
+            let _project = project;
+            panic!("Test failed mid-way");
+        }));
+
+        assert!(result.is_err());
+        assert!(!path.exists());
+    }
+}